@@ -0,0 +1,68 @@
+// ============================================================================
+// em(π)trio MP3 Player — analysis.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Tempo estimation without an FFT: the rises in loudness of every 5 ms
+// window form an onset curve, and the lag at which that curve best matches
+// itself (autocorrelation) is the beat period.
+// ============================================================================
+
+/// Length of the loudness windows, in seconds
+const WINDOW_SECS: f32 = 0.005;
+
+/// Tempo range searched; a beat outside it is found at a multiple inside it
+const MIN_BPM: f32 = 70.0;
+const MAX_BPM: f32 = 180.0;
+
+/// Estimated tempo of mono `samples`, in beats per minute. `None` if there is too
+/// little audio (a few seconds are needed) or no regular beat in it.
+pub fn estimate_bpm(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let window = ((sample_rate as f32 * WINDOW_SECS) as usize).max(1);
+    let windows_per_sec = sample_rate as f32 / window as f32;
+
+    // Onset strength: how much louder each window is than the one before
+    let energy: Vec<f32> = samples
+        .chunks_exact(window)
+        .map(|chunk| chunk.iter().map(|s| s * s).sum::<f32>() / window as f32)
+        .collect();
+    let rises: Vec<f32> = energy.windows(2).map(|pair| (pair[1] - pair[0]).max(0.0)).collect();
+    // Smoothed, so beats whose period falls between whole windows still line up
+    let mut onsets: Vec<f32> = rises.windows(5)
+        .map(|w| (w[0] + 2.0 * w[1] + 3.0 * w[2] + 2.0 * w[3] + w[4]) / 9.0)
+        .collect();
+    let mean = onsets.iter().sum::<f32>() / onsets.len().max(1) as f32;
+    onsets.iter_mut().for_each(|onset| *onset -= mean);
+
+    let min_lag = (windows_per_sec * 60.0 / MAX_BPM).floor().max(1.0) as usize;
+    let max_lag = (windows_per_sec * 60.0 / MIN_BPM).ceil() as usize;
+    if onsets.len() < max_lag * 4 {
+        return None;
+    }
+
+    let correlation = |lag: usize| -> f32 {
+        let pairs = onsets.len() - lag;
+        onsets.iter().zip(&onsets[lag..]).map(|(a, b)| a * b).sum::<f32>() / pairs as f32
+    };
+    let scores: Vec<f32> = (min_lag - 1..=max_lag + 1).map(correlation).collect();
+
+    // Best lag within the range; its neighbours on both sides are scored too
+    let (best, &score) = scores[1..scores.len() - 1]
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(i, score)| (i + 1, score))?;
+    if score <= 0.0 {
+        return None;
+    }
+
+    // Fit a parabola through the peak and its neighbours for a lag between whole windows
+    let (left, right) = (scores[best - 1], scores[best + 1]);
+    let curvature = left - 2.0 * score + right;
+    let shift = if curvature < 0.0 { 0.5 * (left - right) / curvature } else { 0.0 };
+    let lag = (min_lag - 1 + best) as f32 + shift;
+
+    Some(60.0 * windows_per_sec / lag)
+}
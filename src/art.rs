@@ -0,0 +1,108 @@
+// ============================================================================
+// em(π)trio MP3 Player — art.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Album art thumbnails: decodes embedded (APIC) or directory-level cover
+// images and scales them down to small greyscale bitmaps that the TUI can
+// draw with braille characters. The art panel draws the playing track's cover
+// in color with half-block characters, two pixels per cell.
+// ============================================================================
+
+use std::env;
+use std::path::Path;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+use crate::tagread;
+
+/// Size the cover is reduced to once when loaded, so drawing it every frame stays cheap
+const ART_SOURCE_SIZE: u32 = 64;
+
+/// Width and height of a thumbnail, in pixels
+pub const THUMBNAIL_SIZE: u32 = 18;
+
+/// Columns and rows of braille characters a thumbnail is drawn in, 2×4 pixels each
+pub const THUMBNAIL_COLS: u16 = THUMBNAIL_SIZE.div_ceil(2) as u16;
+pub const THUMBNAIL_ROWS: u16 = THUMBNAIL_SIZE.div_ceil(4) as u16;
+
+/// Bit of each dot in a braille character, by pixel row and column within the cell
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Decode an image and scale it to a `THUMBNAIL_SIZE`² greyscale bitmap,
+/// one byte per pixel, row by row. Returns `None` for undecodable data.
+pub fn make_thumbnail(image_data: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(image_data).ok()?;
+    let scaled = image.resize_exact(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle);
+    Some(scaled.to_luma8().into_raw())
+}
+
+/// Draw a thumbnail from `make_thumbnail` in braille, with a dot for every pixel
+/// brighter than the thumbnail's average
+pub fn render_thumbnail(pixels: &[u8]) -> Vec<Line<'static>> {
+    let size = THUMBNAIL_SIZE as usize;
+    let average = pixels.iter().map(|&p| p as usize).sum::<usize>() / pixels.len().max(1);
+    let lit = |x: usize, y: usize| {
+        x < size && pixels.get(y * size + x).is_some_and(|&p| p as usize > average)
+    };
+
+    (0..THUMBNAIL_ROWS as usize)
+        .map(|row| {
+            let cells: String = (0..THUMBNAIL_COLS as usize)
+                .map(|col| {
+                    let mut bits = 0;
+                    for (dy, dots) in BRAILLE_DOTS.iter().enumerate() {
+                        for (dx, dot) in dots.iter().enumerate() {
+                            if lit(col * 2 + dx, row * 4 + dy) {
+                                bits |= dot;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).unwrap_or(' ')
+                })
+                .collect();
+            Line::from(cells)
+        })
+        .collect()
+}
+
+/// The cover of a track: its embedded art, else `dir_art` (a cover image file
+/// of its directory), reduced to at most ART_SOURCE_SIZE² pixels
+pub fn extract_album_art(path: &Path, dir_art: Option<&Path>) -> Option<DynamicImage> {
+    let data = tagread::read_cover_art(path)
+        .or_else(|| std::fs::read(dir_art?).ok())?;
+    let image = image::load_from_memory(&data).ok()?;
+    Some(image.thumbnail(ART_SOURCE_SIZE, ART_SOURCE_SIZE))
+}
+
+/// True when the terminal announces 24-bit color through `COLORTERM`
+pub fn truecolor_supported() -> bool {
+    env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+}
+
+/// Draw an image into `width`×`height` cells: each cell is an upper half block
+/// with the top pixel as foreground and the bottom pixel as background color
+pub fn render_art(img: &DynamicImage, width: u16, height: u16) -> Vec<Line<'static>> {
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+    let scaled = img.resize_exact(width as u32, height as u32 * 2, FilterType::Triangle);
+    let rgb = |x: u32, y: u32| {
+        let [r, g, b, _] = scaled.get_pixel(x, y).0;
+        Color::Rgb(r, g, b)
+    };
+
+    (0..height as u32)
+        .map(|row| {
+            let spans: Vec<Span> = (0..width as u32)
+                .map(|x| Span::styled("▀", Style::default().fg(rgb(x, row * 2)).bg(rgb(x, row * 2 + 1))))
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
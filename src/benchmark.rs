@@ -0,0 +1,140 @@
+// ============================================================================
+// em(π)trio MP3 Player — benchmark.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Implements `empitrio --benchmark [--dir <path>]`, which times the slow
+// parts of the player (directory scan, tag reading, time to first audio and
+// the library size scan) and prints them as a table, so users with large
+// libraries can see where the time goes.
+// ============================================================================
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{env, io};
+
+use crate::player::Player;
+use crate::{is_audio_file, tagread, App};
+
+/// How long to wait for the first track to report progress
+const FIRST_AUDIO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One measured phase of the report
+struct Phase {
+    name: &'static str,
+    elapsed: Option<Duration>, // None if the phase could not run
+    items: usize,              // Files handled, for the throughput column (0 = not applicable)
+}
+
+/// Run the benchmark with the arguments following `--benchmark`.
+/// Returns the process exit code.
+pub fn run(args: &[String]) -> io::Result<i32> {
+    let mut dir: Option<PathBuf> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => dir = args.next().map(PathBuf::from),
+            other => {
+                eprintln!("Unknown benchmark option: {other}");
+                eprintln!("Usage: empitrio --benchmark [--dir <path>]");
+                return Ok(1);
+            }
+        }
+    }
+
+    let dir = match dir {
+        Some(dir) => dir,
+        None => env::current_dir()?,
+    };
+
+    let mut phases = Vec::new();
+
+    // Directory scan
+    let start = Instant::now();
+    let player = Player::new();
+    let mut app = App::new_at_dir(dir.clone(), player.clone())?;
+    app.wait_for_dir();
+    phases.push(Phase { name: "Directory scan", elapsed: Some(start.elapsed()), items: app.files.len() });
+
+    let tracks: Vec<PathBuf> = app.files.iter()
+        .map(|f| dir.join(f))
+        .filter(|path| path.is_file() && is_audio_file(path))
+        .collect();
+
+    // Tag reading
+    let start = Instant::now();
+    for path in &tracks {
+        tagread::read_tags(path);
+    }
+    phases.push(Phase { name: "Tag read", elapsed: Some(start.elapsed()), items: tracks.len() });
+
+    // Time to first audio: decoding the first track and opening the output device
+    let first_audio = tracks.first().and_then(|path| {
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        player.play_file(path, tx).ok()?;
+        let elapsed = rx.recv_timeout(FIRST_AUDIO_TIMEOUT).ok().map(|_| start.elapsed());
+        let _ = player.stop();
+        elapsed
+    });
+    phases.push(Phase { name: "Time to first audio", elapsed: first_audio, items: 0 });
+
+    // Library size scan (runs in the background, so wait for it)
+    let start = Instant::now();
+    app.compute_library_size();
+    while app.total_library_tracks_cached.is_none() {
+        thread::sleep(Duration::from_millis(1));
+        app.poll_library_size();
+    }
+    phases.push(Phase {
+        name: "Library size scan",
+        elapsed: Some(start.elapsed()),
+        items: app.total_library_tracks_cached.unwrap_or(0),
+    });
+
+    println!("Benchmark of {}", dir.display());
+    println!();
+    print_table(&phases);
+
+    if let Some(kb) = resident_memory_kb() {
+        println!();
+        println!("Resident memory: {:.1} MB", kb as f64 / 1024.0);
+    }
+
+    Ok(0)
+}
+
+/// Print one row per phase: wall time and files per second
+fn print_table(phases: &[Phase]) {
+    let width = phases.iter().map(|p| p.name.len()).max().unwrap_or(0).max("PHASE".len());
+
+    let header = format!("{:<width$}  {:>10}  {:>7}  {:>10}", "PHASE", "TIME", "FILES", "FILES/S");
+    println!("{header}");
+    println!("{}", "-".repeat(header.chars().count()));
+
+    for phase in phases {
+        let time = phase.elapsed
+            .map(|d| format!("{:.1}ms", d.as_secs_f64() * 1000.0))
+            .unwrap_or_else(|| "n/a".into());
+        let files = if phase.items > 0 { phase.items.to_string() } else { "-".into() };
+        let rate = match phase.elapsed {
+            Some(d) if phase.items > 0 && !d.is_zero() => format!("{:.0}", phase.items as f64 / d.as_secs_f64()),
+            _ => "-".into(),
+        };
+        println!("{:<width$}  {:>10}  {:>7}  {:>10}", phase.name, time, files, rate);
+    }
+}
+
+/// Resident set size of this process in kB; only available on Linux
+fn resident_memory_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status.lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
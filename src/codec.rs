@@ -0,0 +1,167 @@
+// ============================================================================
+// em(π)trio MP3 Player — codec.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Reads basic codec information (format and bitrate) straight from the file
+// headers, without decoding any audio. MP3 bitrates come from the first frame
+// header, with Xing/VBRI headers marking VBR files; OGG Vorbis reports its
+// nominal bitrate and FLAC is lossless, so it only reports its format. M4A and
+// Opus files are only recognized, since they can't be played.
+// ============================================================================
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of the file is read when looking for the first MP3 frame
+const HEADER_READ_LIMIT: u64 = 256 * 1024;
+
+/// Container/codec of an audio file, as far as the header tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Mp3,
+    Flac,
+    Ogg,
+    M4a, // Only playable when built with the `aac` feature
+    Opus, // Ogg Opus; listed but not playable
+    Unknown,
+}
+
+/// Codec details of a single audio file
+#[derive(Debug, Clone)]
+pub struct CodecInfo {
+    pub format: AudioFormat,
+    pub bitrate_kbps: Option<u32>, // Constant (MP3) or nominal (OGG) bitrate, if known
+    pub vbr: bool,                 // MP3 with a Xing or VBRI header
+}
+
+impl CodecInfo {
+    /// Short badge for the file list, e.g. `[320k]`, `[VBR]`, `[FLAC]` or `[M4A?]`;
+    /// a `?` marks formats that can't be played.
+    /// Returns `None` when the bitrate is unknown.
+    pub fn badge(&self) -> Option<String> {
+        match self.format {
+            AudioFormat::Flac => Some("[FLAC]".into()),
+            AudioFormat::M4a if cfg!(feature = "aac") => Some("[M4A]".into()),
+            AudioFormat::M4a => Some("[M4A?]".into()),
+            AudioFormat::Opus => Some("[OPUS?]".into()),
+            AudioFormat::Mp3 if self.vbr => Some("[VBR]".into()),
+            _ => self.bitrate_kbps.map(|kbps| format!("[{}k]", kbps)),
+        }
+    }
+}
+
+/// Read the codec information of a file from its headers.
+pub fn read_codec_info<P: AsRef<Path>>(path: P) -> CodecInfo {
+    let mut data = Vec::new();
+    let read = File::open(path)
+        .and_then(|file| file.take(HEADER_READ_LIMIT).read_to_end(&mut data));
+
+    let unknown = CodecInfo { format: AudioFormat::Unknown, bitrate_kbps: None, vbr: false };
+    if read.is_err() {
+        return unknown;
+    }
+
+    if data.starts_with(b"fLaC") {
+        CodecInfo { format: AudioFormat::Flac, bitrate_kbps: None, vbr: false }
+    } else if data.get(4..8) == Some(b"ftyp") {
+        // MPEG-4 container; assumed to hold AAC
+        CodecInfo { format: AudioFormat::M4a, bitrate_kbps: None, vbr: false }
+    } else if data.starts_with(b"OggS") && data.windows(8).take(64).any(|w| w == b"OpusHead") {
+        CodecInfo { format: AudioFormat::Opus, bitrate_kbps: None, vbr: false }
+    } else if data.starts_with(b"OggS") {
+        CodecInfo { format: AudioFormat::Ogg, bitrate_kbps: ogg_nominal_bitrate(&data), vbr: false }
+    } else {
+        mp3_info(&data).unwrap_or(unknown)
+    }
+}
+
+/// Parse the first MPEG audio frame header after any ID3v2 tag
+fn mp3_info(data: &[u8]) -> Option<CodecInfo> {
+    let start = id3v2_size(data);
+
+    (start..data.len().saturating_sub(4)).find_map(|pos| {
+        let header = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
+        let frame = FrameHeader::parse(header)?;
+
+        // "Xing"/"VBRI" mark VBR files; "Info" is the same header written for CBR files
+        let xing = pos + 4 + frame.side_info_len();
+        let vbri = pos + 4 + 32;
+        let vbr = data.get(xing..xing + 4) == Some(b"Xing") || data.get(vbri..vbri + 4) == Some(b"VBRI");
+
+        Some(CodecInfo {
+            format: AudioFormat::Mp3,
+            bitrate_kbps: Some(frame.bitrate_kbps),
+            vbr,
+        })
+    })
+}
+
+/// Size of a leading ID3v2 tag (header, body and optional footer), or 0
+fn id3v2_size(data: &[u8]) -> usize {
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return 0;
+    }
+    // Tag size is a 28-bit "syncsafe" integer
+    let size = data[6..10].iter().fold(0usize, |acc, b| (acc << 7) | (*b as usize & 0x7f));
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    10 + size + footer
+}
+
+struct FrameHeader {
+    mpeg1: bool,
+    mono: bool,
+    bitrate_kbps: u32,
+}
+
+impl FrameHeader {
+    /// Decode a Layer III frame header, rejecting anything that isn't a valid one
+    fn parse(header: u32) -> Option<Self> {
+        const MPEG1_L3: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+        const MPEG2_L3: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+        let sync = header >> 21 == 0x7ff;
+        let version = (header >> 19) & 0b11; // 3 = MPEG1, 2 = MPEG2, 0 = MPEG2.5
+        let layer = (header >> 17) & 0b11; // 1 = Layer III
+        let bitrate_index = ((header >> 12) & 0b1111) as usize;
+        let sample_rate_index = (header >> 10) & 0b11;
+        let channel_mode = (header >> 6) & 0b11;
+
+        if !sync || version == 1 || layer != 1 || bitrate_index == 0 || bitrate_index == 15
+            || sample_rate_index == 3
+        {
+            return None;
+        }
+
+        let mpeg1 = version == 3;
+        let table = if mpeg1 { &MPEG1_L3 } else { &MPEG2_L3 };
+        Some(Self {
+            mpeg1,
+            mono: channel_mode == 3,
+            bitrate_kbps: table[bitrate_index],
+        })
+    }
+
+    /// Length of the side information that precedes a Xing header
+    fn side_info_len(&self) -> usize {
+        match (self.mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) => 17,
+            (false, false) => 17,
+            (false, true) => 9,
+        }
+    }
+}
+
+/// Nominal bitrate from the Vorbis identification header of an OGG stream
+fn ogg_nominal_bitrate(data: &[u8]) -> Option<u32> {
+    let pos = data.windows(7).position(|w| w == b"\x01vorbis")?;
+    // packet type + "vorbis" (7), version (4), channels (1), sample rate (4), max bitrate (4)
+    let offset = pos + 7 + 4 + 1 + 4 + 4;
+    let bytes = data.get(offset..offset + 4)?;
+    let nominal = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (nominal > 0).then_some(nominal as u32 / 1000)
+}
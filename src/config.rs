@@ -0,0 +1,316 @@
+// ============================================================================
+// em(π)trio MP3 Player — config.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// User configuration loaded from `<config dir>/empitrio/config.toml` and
+// session state persisted to `<config dir>/empitrio/state.toml`, bookmarks
+// to `<config dir>/empitrio/bookmarks.toml`. The config
+// also holds the key bindings of the main actions.
+// Missing files or unknown keys fall back to defaults, so empitrio always
+// starts even without any configuration.
+// ============================================================================
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+
+use crate::theme::ThemeConfig;
+
+/// User settings read from `config.toml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub music_dir: Option<String>,      // Directory opened by `:reset` (defaults to the startup directory)
+    pub startup_dir: Option<String>,    // Directory opened on start when none is given on the command line
+    pub default_volume: f32,            // Volume on startup (1.0 = 100%)
+    pub remember_volume_per_dir: bool,  // Restore the last volume used in each directory
+    pub crossfade_enabled: bool,        // Fade into the next track before the current one ends
+    pub crossfade_secs: u64,            // Length of the crossfade, in seconds (1 to 5)
+    pub gapless: bool,                  // Start the next track without a gap
+    pub replay_gain: bool,              // Normalize the volume with the tracks' ReplayGain tags
+    pub show_album_art: bool,           // Show a thumbnail of the selected track's cover next to the list
+    pub show_bitrate_column: bool,      // Show a bitrate badge next to each file in the list
+    pub sink_listened: bool,            // Sort files marked as listened to the bottom of the list
+    pub auto_mark_listened: bool,       // Mark tracks as listened when they finish playing
+    pub podcast_dirs: Vec<String>,      // Directories whose files skip the intro on start
+    pub podcast_skip_secs: u64,         // Seconds skipped at the start of podcast files
+    pub now_playing_file: Option<String>, // File updated with the current track for status bars
+    pub now_playing_format: String,     // Line format: {artist}, {title}, {album}, {file}, {state}
+    pub eq_presets: HashMap<String, Vec<f32>>, // Saved equalizer gains (dB per band), by name
+    pub status_bar_lines: u16,          // Status messages shown at once (1 to 5; `E` changes it)
+    pub render_fps: u8,                 // Screen refreshes per second while idle (1 to 60)
+    pub save_history: bool,             // Keep the playback history in a log across restarts
+    pub autoplay: AutoplayConfig,       // Start playing on launch
+    pub keys: KeyBindings,              // Keys of the main actions
+    pub theme: ThemeConfig,             // Color overrides of the default theme
+    #[serde(skip)]
+    load_error: Option<String>,         // Why the file couldn't be parsed; `save` leaves such a file alone
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            music_dir: None,
+            startup_dir: None,
+            default_volume: 1.0,
+            remember_volume_per_dir: false,
+            crossfade_enabled: false,
+            crossfade_secs: 5,
+            gapless: false,
+            replay_gain: true,
+            show_album_art: false,
+            show_bitrate_column: false,
+            sink_listened: false,
+            auto_mark_listened: false,
+            podcast_dirs: Vec::new(),
+            podcast_skip_secs: 0,
+            now_playing_file: None,
+            now_playing_format: "{artist} - {title}".into(),
+            eq_presets: HashMap::new(),
+            status_bar_lines: 1,
+            render_fps: 4,
+            save_history: false,
+            autoplay: AutoplayConfig::default(),
+            keys: KeyBindings::default(),
+            theme: ThemeConfig::default(),
+            load_error: None,
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, or defaults if it is missing or invalid.
+    pub fn load() -> Self {
+        let Some(text) = config_path("config.toml").and_then(|path| fs::read_to_string(path).ok()) else {
+            return Self::default();
+        };
+        toml::from_str(&text).unwrap_or_else(|e: toml::de::Error| {
+            let error = match e.span() {
+                Some(span) => format!("line {}: {}", text[..span.start].matches('\n').count() + 1, e.message()),
+                None => e.message().to_string(),
+            };
+            Self { load_error: Some(error), ..Self::default() }
+        })
+    }
+
+    /// Why the config file couldn't be parsed, if it couldn't; defaults are in use then
+    pub fn load_error(&self) -> Option<&str> {
+        self.load_error.as_deref()
+    }
+
+    /// Write the config file, creating the config directory if needed. A file that
+    /// failed to load is never overwritten, as that would lose the user's settings.
+    pub fn save(&self) -> Result<(), String> {
+        if self.load_error.is_some() {
+            return Err("config.toml has errors; fix them before saving settings".into());
+        }
+        save_toml("config.toml", self)
+    }
+
+    /// The action bound to a key, if any
+    pub fn action_for(&self, key: KeyCode) -> Option<Action> {
+        self.keys.bindings()
+            .into_iter()
+            .find(|(_, names)| names.iter().any(|name| parse_key(name) == Some(key)))
+        .map(|(action, _)| action)
+    }
+}
+
+/// The `[autoplay]` table: what to play when empitrio starts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoplayConfig {
+    pub enabled: bool,
+    pub mode: AutoplayMode,
+}
+
+/// Which track autoplay starts with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoplayMode {
+    #[default]
+    First,      // The first track of the start directory
+    LastPlayed, // The track playing when empitrio last exited
+}
+
+/// Actions that can be bound to keys in the `[keys]` table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Pause,
+    Next,
+    Previous,
+    Select,
+    SeekForward,
+    SeekBackward,
+    VolumeUp,
+    VolumeDown,
+}
+
+impl Action {
+    /// Description shown in the help overlay
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Pause => "Pause/Play",
+            Action::Next => "Move down",
+            Action::Previous => "Move up",
+            Action::Select => "Play / open folder",
+            Action::SeekForward => "Seek forward",
+            Action::SeekBackward => "Seek backward",
+            Action::VolumeUp => "Volume up",
+            Action::VolumeDown => "Volume down",
+        }
+    }
+}
+
+/// Keys of each action: single characters (`"q"`, `"+"`) or key names
+/// (`"Esc"`, `"Enter"`, `"Space"`, `"Up"`, `"PageDown"`, `"F1"`, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub quit: Vec<String>,
+    pub pause: Vec<String>,
+    pub next: Vec<String>,
+    pub previous: Vec<String>,
+    pub select: Vec<String>,
+    pub seek_forward: Vec<String>,
+    pub seek_backward: Vec<String>,
+    pub volume_up: Vec<String>,
+    pub volume_down: Vec<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let keys = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            quit: keys(&["q", "Esc"]),
+            pause: keys(&["p", "Space"]),
+            next: keys(&["Down", "j"]),
+            previous: keys(&["Up", "k"]),
+            select: keys(&["Enter"]),
+            seek_forward: keys(&["Right"]),
+            seek_backward: keys(&["Left", "h"]),
+            volume_up: keys(&["+", "="]),
+            volume_down: keys(&["-"]),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Every action with its keys, in help overlay order
+    pub fn bindings(&self) -> [(Action, &[String]); 9] {
+        [
+            (Action::Quit, &self.quit),
+            (Action::Pause, &self.pause),
+            (Action::Next, &self.next),
+            (Action::Previous, &self.previous),
+            (Action::Select, &self.select),
+            (Action::SeekForward, &self.seek_forward),
+            (Action::SeekBackward, &self.seek_backward),
+            (Action::VolumeUp, &self.volume_up),
+            (Action::VolumeDown, &self.volume_down),
+        ]
+    }
+}
+
+/// Parse a key name from the config; names are case-insensitive, single characters are not
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    let key = match name.to_ascii_lowercase().as_str() {
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        other => {
+            let number = other.strip_prefix('f')?.parse().ok()?;
+            KeyCode::F(number)
+        }
+    };
+    Some(key)
+}
+
+/// Session state written on exit and restored on the next start
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub dir_volumes: HashMap<PathBuf, f32>,
+    pub recent_dirs: Vec<PathBuf>,
+    pub last_dir: Option<PathBuf>, // Directory open when empitrio last exited
+    pub last_selected: usize,      // Selected entry in that directory
+    pub last_played: Option<PathBuf>, // Track started last, for `mode = "last_played"` autoplay
+}
+
+impl State {
+    /// Load the state file, or an empty state if it is missing or invalid.
+    pub fn load() -> Self {
+        config_path("state.toml")
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the state file, creating the config directory if needed.
+    pub fn save(&self) -> Result<(), String> {
+        save_toml("state.toml", self)
+    }
+}
+
+/// Bookmarked files and folders (`b`), saved on every change
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bookmarks {
+    pub paths: Vec<PathBuf>, // Absolute paths, oldest first
+}
+
+impl Bookmarks {
+    /// Load the bookmarks file without paths that no longer exist; empty if it
+    /// is missing or invalid.
+    pub fn load() -> Self {
+        let mut bookmarks: Self = config_path("bookmarks.toml")
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+        bookmarks.paths.retain(|path| path.exists());
+        bookmarks
+    }
+
+    /// Write the bookmarks file, creating the config directory if needed.
+    pub fn save(&self) -> Result<(), String> {
+        save_toml("bookmarks.toml", self)
+    }
+}
+
+/// Serialize a value to a TOML file in the config directory
+fn save_toml<T: Serialize>(file_name: &str, value: &T) -> Result<(), String> {
+    let path = config_path(file_name).ok_or("No config directory available")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {parent:?}: {e}"))?;
+    }
+    let text = toml::to_string(value).map_err(|e| format!("Failed to serialize {file_name}: {e}"))?;
+    fs::write(&path, text).map_err(|e| format!("Failed to write {path:?}: {e}"))
+}
+
+/// Path of a file inside the empitrio config directory
+pub fn config_path(file_name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("empitrio").join(file_name))
+}
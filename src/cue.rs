@@ -0,0 +1,148 @@
+// ============================================================================
+// em(π)trio MP3 Player — cue.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Reads `.cue` sheets that split one album-length file into tracks. A track
+// starts at its `INDEX 01`; the `INDEX 00` before it marks the pre-gap, which
+// still belongs to the previous track. Times are `mm:ss:ff` with 75 frames
+// per second.
+// ============================================================================
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const FRAMES_PER_SEC: u64 = 75;
+
+/// One track of a cue sheet: a section of `file` from `start_offset` to the
+/// start of the next track (or the end of the file)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueTrack {
+    pub title: String,
+    pub performer: Option<String>, // The track's own, else the album's
+    pub file: PathBuf,             // Audio file the track is in
+    pub start_offset: Duration,
+}
+
+/// True for `.cue` files
+pub fn is_cue_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("cue"))
+        .unwrap_or(false)
+}
+
+/// The tracks of a cue sheet, in order. Tracks without an index are skipped;
+/// one with only a pre-gap starts at its `INDEX 00`. Empty if the sheet can't be read.
+pub fn parse_cue(path: &Path) -> Vec<CueTrack> {
+    // Cue sheets are often Latin-1, so don't fail on invalid UTF-8
+    let Ok(bytes) = fs::read(path) else {
+        return Vec::new();
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let mut tracks = Vec::new();
+    let mut album_performer = None;
+    let mut file = None;
+    let mut current: Option<PendingTrack> = None;
+
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match keyword.to_ascii_uppercase().as_str() {
+            "FILE" => file = Some(base.join(file_name(rest))),
+            "TRACK" => {
+                tracks.extend(current.take().and_then(|track| track.finish(&album_performer)));
+                let number = rest.split_whitespace().next().unwrap_or_default();
+                current = file.clone().map(|file| PendingTrack::new(number, file));
+            }
+            "TITLE" => {
+                if let Some(track) = current.as_mut() {
+                    track.title = Some(quoted(rest).to_string());
+                }
+            }
+            "PERFORMER" => match current.as_mut() {
+                Some(track) => track.performer = Some(quoted(rest).to_string()),
+                None => album_performer = Some(quoted(rest).to_string()),
+            },
+            "INDEX" => {
+                let (number, time) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if let (Some(track), Some(time)) = (current.as_mut(), parse_time(time.trim())) {
+                    match number {
+                        "00" => track.pregap = Some(time),
+                        "01" => track.start = Some(time),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    tracks.extend(current.and_then(|track| track.finish(&album_performer)));
+    tracks
+}
+
+/// Index of the track playing at `position` in `file`: the last one that has started
+pub fn current_track(tracks: &[CueTrack], file: &Path, position: Duration) -> Option<usize> {
+    tracks.iter()
+        .enumerate()
+        .filter(|(_, track)| track.file == file && track.start_offset <= position)
+        .map(|(i, _)| i)
+        .next_back()
+}
+
+/// A track while its lines are being read
+struct PendingTrack {
+    number: String,
+    file: PathBuf,
+    title: Option<String>,
+    performer: Option<String>,
+    pregap: Option<Duration>, // INDEX 00
+    start: Option<Duration>,  // INDEX 01
+}
+
+impl PendingTrack {
+    fn new(number: &str, file: PathBuf) -> Self {
+        Self { number: number.to_string(), file, title: None, performer: None, pregap: None, start: None }
+    }
+
+    fn finish(self, album_performer: &Option<String>) -> Option<CueTrack> {
+        Some(CueTrack {
+            start_offset: self.start.or(self.pregap)?,
+            title: self.title.unwrap_or_else(|| format!("Track {}", self.number)),
+            performer: self.performer.or_else(|| album_performer.clone()),
+            file: self.file,
+        })
+    }
+}
+
+/// A value with its surrounding quotes, if any, removed
+fn quoted(value: &str) -> &str {
+    match value.strip_prefix('"').and_then(|rest| rest.split_once('"')) {
+        Some((inner, _)) => inner,
+        None => value,
+    }
+}
+
+/// The file name of a `FILE` line, which ends with the file type (`FILE "album.mp3" MP3`)
+fn file_name(value: &str) -> &str {
+    if value.starts_with('"') {
+        quoted(value)
+    } else {
+        value.rsplit_once(char::is_whitespace).map_or(value, |(name, _)| name.trim())
+    }
+}
+
+/// `mm:ss:ff` as a duration
+fn parse_time(time: &str) -> Option<Duration> {
+    let mut parts = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if seconds >= 60 || frames >= FRAMES_PER_SEC || parts.next().is_some() {
+        return None;
+    }
+    Some(Duration::from_secs(minutes * 60 + seconds) + Duration::from_millis(frames * 1000 / FRAMES_PER_SEC))
+}
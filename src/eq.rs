@@ -0,0 +1,193 @@
+// ============================================================================
+// em(π)trio MP3 Player — eq.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// A small graphic equalizer: one peaking biquad filter per band, applied to
+// the decoded samples as a rodio `Source`. The filter chain is built when a
+// track starts, so changing the EQ restarts playback with the new chain.
+// ============================================================================
+
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Center frequencies of the equalizer bands, in Hz
+pub const BAND_FREQUENCIES: [f32; 5] = [60.0, 230.0, 910.0, 3600.0, 14000.0];
+
+/// Presets available even when the config defines none
+pub const BUILTIN_PRESETS: [(&str, [f32; 5]); 4] = [
+    ("flat", [0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("bass_boost", [6.0, 4.0, 0.0, 0.0, 0.0]),
+    ("treble_boost", [0.0, 0.0, 0.0, 4.0, 6.0]),
+    ("vocal", [-2.0, 0.0, 4.0, 3.0, 0.0]),
+];
+
+/// One equalizer band
+#[derive(Debug, Clone, Copy)]
+pub struct EqBand {
+    pub freq_hz: f32,
+    pub gain_db: f32,
+}
+
+/// Gains of all bands; a flat equalizer leaves the audio untouched
+#[derive(Debug, Clone)]
+pub struct Equalizer {
+    pub bands: Vec<EqBand>,
+}
+
+impl Default for Equalizer {
+    fn default() -> Self {
+        Self::from_gains(&[0.0; BAND_FREQUENCIES.len()])
+    }
+}
+
+impl Equalizer {
+    /// Build an equalizer from per-band gains in dB. Missing bands are flat,
+    /// extra values are ignored.
+    pub fn from_gains(gains: &[f32]) -> Self {
+        let bands = BAND_FREQUENCIES.iter()
+            .enumerate()
+            .map(|(i, &freq_hz)| EqBand {
+                freq_hz,
+                gain_db: gains.get(i).copied().unwrap_or(0.0),
+            })
+            .collect();
+        Self { bands }
+    }
+
+    /// Gain of every band, in dB
+    pub fn gains(&self) -> Vec<f32> {
+        self.bands.iter().map(|band| band.gain_db).collect()
+    }
+}
+
+/// Gains of a built-in preset, if `name` is one
+pub fn builtin_preset(name: &str) -> Option<Vec<f32>> {
+    BUILTIN_PRESETS.iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|(_, gains)| gains.to_vec())
+}
+
+/// Peaking filter coefficients (RBJ audio EQ cookbook), normalized by a0
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    fn peaking(freq_hz: f32, gain_db: f32, sample_rate: u32) -> Self {
+        const Q: f32 = 1.0;
+
+        let a = 10f32.powf(gain_db / 40.0);
+        // Keep the center frequency below Nyquist for low sample rates
+        let freq_hz = freq_hz.min(sample_rate as f32 * 0.45);
+        let w0 = 2.0 * PI * freq_hz / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * Q);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha / a;
+
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos_w0) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos_w0) / a0,
+            a2: (1.0 - alpha / a) / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+
+    fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.x2 = 0.0;
+        self.y1 = 0.0;
+        self.y2 = 0.0;
+    }
+}
+
+/// Source wrapper that runs every channel through the equalizer bands
+pub struct EqSource<S> {
+    inner: S,
+    filters: Vec<Vec<Biquad>>, // [channel][band]
+    channel: usize,
+}
+
+impl<S: Source<Item = f32>> EqSource<S> {
+    pub fn new(inner: S, eq: &Equalizer) -> Self {
+        let sample_rate = inner.sample_rate();
+        let channels = inner.channels().max(1) as usize;
+
+        // Flat bands are skipped entirely
+        let bands: Vec<Biquad> = eq.bands.iter()
+            .filter(|band| band.gain_db != 0.0)
+            .map(|band| Biquad::peaking(band.freq_hz, band.gain_db, sample_rate))
+            .collect();
+
+        Self {
+            inner,
+            filters: vec![bands; channels],
+            channel: 0,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for EqSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        let channel = self.channel;
+        self.channel = (channel + 1) % self.filters.len();
+
+        Some(self.filters[channel].iter_mut().fold(sample, |s, filter| filter.process(s)))
+    }
+}
+
+impl<S: Source<Item = f32>> Source for EqSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+        self.filters.iter_mut().flatten().for_each(Biquad::reset);
+        self.channel = 0;
+        Ok(())
+    }
+}
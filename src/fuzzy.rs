@@ -0,0 +1,48 @@
+// ============================================================================
+// em(π)trio MP3 Player — fuzzy.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Small fuzzy matcher used by the pickers: the pattern must appear in the
+// text as a case-insensitive subsequence. Consecutive matches and matches at
+// word starts score higher, skipped characters cost a little.
+// ============================================================================
+
+/// A successful match: higher scores are better, `positions` are the char
+/// indices of the matched characters in the text (for highlighting)
+#[derive(Debug, Clone)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+/// Score `text` against `pattern`, or `None` if the pattern doesn't match.
+/// An empty pattern matches everything with a score of 0.
+pub fn fuzzy_search(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let mut score = 0i64;
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut next = 0;
+
+    for wanted in pattern {
+        let found = (next..text.len()).find(|&i| text[i].to_lowercase().eq(std::iter::once(wanted)))?;
+
+        score += 16;
+        if positions.last().is_some_and(|&last| last + 1 == found) {
+            score += 8; // consecutive
+        }
+        if found == 0 || matches!(text[found - 1], ' ' | '/' | '_' | '-' | '.') {
+            score += 8; // word start
+        }
+        score -= (found - next) as i64; // skipped characters
+
+        positions.push(found);
+        next = found + 1;
+    }
+
+    Some(FuzzyMatch { score, positions })
+}
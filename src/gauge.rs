@@ -0,0 +1,120 @@
+// ============================================================================
+// em(π)trio MP3 Player — gauge.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// The progress bar: the played part of the track shaded from green through
+// yellow to red, tick marks at chosen positions (the A-B loop points) and the
+// percentage in the middle. Streams, with no known length, get a spinner.
+// ============================================================================
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Widget},
+};
+
+pub const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+// How long each spinner frame shows, in milliseconds of playback
+const SPINNER_FRAME_MS: u64 = 100;
+
+pub struct TrackGauge<'a> {
+    elapsed: u64,             // Milliseconds played
+    total: u64,               // Track length in milliseconds; zero if unknown
+    marks: &'a [(u64, Color)], // Positions (ms) to tick, with their colour
+    block: Option<Block<'a>>,
+    label_style: Style,       // Label over the unplayed part
+}
+
+impl<'a> TrackGauge<'a> {
+    pub fn new(elapsed: u64, total: u64, marks: &'a [(u64, Color)]) -> Self {
+        Self { elapsed, total, marks, block: None, label_style: Style::default() }
+    }
+
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn label_style(mut self, style: Style) -> Self {
+        self.label_style = style;
+        self
+    }
+
+    /// Column of `position` within `width` cells
+    fn column(&self, position: u64, width: u16) -> u16 {
+        let column = position.min(self.total) as f64 / self.total as f64 * f64::from(width);
+        (column as u16).min(width.saturating_sub(1))
+    }
+
+    fn label(&self) -> String {
+        match self.total {
+            0 if self.elapsed == 0 => String::new(),
+            0 => format!("{} live", SPINNER[(self.elapsed / SPINNER_FRAME_MS) as usize % SPINNER.len()]),
+            total => format!("{}%", self.elapsed.min(total) * 100 / total),
+        }
+    }
+}
+
+/// Colour of the bar at `fraction` (0.0 to 1.0) of the track: green, yellow halfway, then red
+fn zone_color(fraction: f64) -> Color {
+    let red = (fraction * 2.0).min(1.0);
+    let green = ((1.0 - fraction) * 2.0).min(1.0);
+    Color::Rgb((red * 220.0) as u8, (green * 200.0) as u8, 0)
+}
+
+impl Widget for TrackGauge<'_> {
+    fn render(mut self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block.take() {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        if area.is_empty() {
+            return;
+        }
+
+        // Played part, each column in the colour of its zone
+        let filled = match self.total {
+            0 => 0,
+            total => (self.elapsed.min(total) as f64 / total as f64 * f64::from(area.width)) as u16,
+        };
+        for x in 0..filled {
+            let color = zone_color(f64::from(x) / f64::from(area.width));
+            for y in area.top()..area.bottom() {
+                buf[(area.left() + x, y)].set_symbol(" ").set_bg(color);
+            }
+        }
+
+        if self.total > 0 {
+            for &(position, color) in self.marks {
+                let x = area.left() + self.column(position, area.width);
+                for y in area.top()..area.bottom() {
+                    buf[(x, y)].set_symbol("│").set_fg(color);
+                }
+            }
+        }
+
+        // Centered label; dark where it sits on the played part
+        let label = self.label();
+        let width = (label.chars().count() as u16).min(area.width);
+        let left = area.left() + (area.width - width) / 2;
+        let row = area.top() + area.height / 2;
+        for (i, c) in label.chars().take(width as usize).enumerate() {
+            let x = left + i as u16;
+            let style = if x < area.left() + filled {
+                Style::default().fg(Color::Black)
+            } else {
+                self.label_style
+            };
+            buf[(x, row)].set_char(c).set_style(style);
+        }
+    }
+}
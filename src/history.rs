@@ -0,0 +1,88 @@
+// ============================================================================
+// em(π)trio MP3 Player — history.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Playback history log at `<data dir>/empitrio/history.log`: one
+// `<unix timestamp>,<path>` line per played track, oldest first.
+// ============================================================================
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Entries kept in memory (and loaded from the log)
+pub const MAX_HISTORY: usize = 500;
+
+/// A played track and when it started, as seconds since the Unix epoch
+pub type HistoryEntry = (PathBuf, u64);
+
+/// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("empitrio").join("history.log"))
+}
+
+/// The last MAX_HISTORY entries of the log; empty if there is none.
+/// Malformed lines are skipped.
+pub fn load() -> Vec<HistoryEntry> {
+    let Some(text) = history_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+
+    let entries: Vec<HistoryEntry> = text.lines()
+        .filter_map(|line| {
+            // The path goes last, so commas in it don't matter
+            let (timestamp, path) = line.split_once(',')?;
+            Some((PathBuf::from(path), timestamp.parse().ok()?))
+        })
+        .collect();
+
+    let skip = entries.len().saturating_sub(MAX_HISTORY);
+    entries.into_iter().skip(skip).collect()
+}
+
+/// Append one entry to the log, creating it if needed
+pub fn append(entry: &HistoryEntry) -> io::Result<()> {
+    let path = history_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory available"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{},{}", entry.1, entry.0.to_string_lossy())
+}
+
+/// Delete the log; a missing log is not an error
+pub fn clear() -> io::Result<()> {
+    let Some(path) = history_path() else {
+        return Ok(());
+    };
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// How long ago a timestamp was, e.g. `just now`, `12m ago`, `3h ago`, `2d ago`
+pub fn time_ago(timestamp: u64) -> String {
+    age(now().saturating_sub(timestamp))
+}
+
+/// An age in seconds in the words of `time_ago`
+pub fn age(secs: u64) -> String {
+    match secs {
+        0..=59 => "just now".into(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
@@ -0,0 +1,120 @@
+// ============================================================================
+// em(π)trio MP3 Player — ipc.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Remote control of a running instance over a Unix domain socket at
+// `$XDG_RUNTIME_DIR/empitrio.sock` (or the temp directory). Clients send one
+// JSON-encoded `IpcCommand` per line; `empitrio --ctl <command>` is such a
+// client.
+// ============================================================================
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+/// Commands a client can send to the running instance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum IpcCommand {
+    PlayPause,
+    Next,
+    Previous,
+    Stop,
+    SetVolume(f32),    // 1.0 = 100%
+    QueueFile(String), // Absolute path of a track to play next
+}
+
+/// Removes the socket file when the server shuts down
+pub struct SocketGuard {
+    path: PathBuf,
+}
+
+impl Drop for SocketGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Path of the control socket
+pub fn socket_path() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join("empitrio.sock")
+}
+
+/// Listen for commands on the control socket in a background thread and pass
+/// them to `tx`. Fails if another instance is already listening.
+pub fn start_ipc_server(tx: Sender<IpcCommand>) -> io::Result<SocketGuard> {
+    let path = socket_path();
+
+    // A socket file nobody answers on is left over from a crash
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "Another instance is running"));
+        }
+        fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                // Malformed lines are ignored; the client gets no reply either way
+                if let Ok(command) = serde_json::from_str(&line) {
+                    if tx.send(command).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(SocketGuard { path })
+}
+
+/// `empitrio --ctl <command>`: send one command to the running instance.
+/// Returns the process exit code.
+pub fn run_ctl(args: &[String]) -> io::Result<i32> {
+    let command = match args {
+        [command] if command == "play-pause" => IpcCommand::PlayPause,
+        [command] if command == "next" => IpcCommand::Next,
+        [command] if command == "previous" => IpcCommand::Previous,
+        [command] if command == "stop" => IpcCommand::Stop,
+        [command, volume] if command == "volume" => match volume.parse() {
+            Ok(volume) => IpcCommand::SetVolume(volume),
+            Err(_) => {
+                eprintln!("Volume must be a number, e.g. 0.8 for 80%");
+                return Ok(1);
+            }
+        },
+        [command, file] if command == "queue" => {
+            // The running instance has its own working directory
+            let file = fs::canonicalize(file)?;
+            IpcCommand::QueueFile(file.to_string_lossy().into_owned())
+        }
+        _ => {
+            eprintln!("Usage: empitrio --ctl play-pause | next | previous | stop | volume <0.0-2.0> | queue <file>");
+            return Ok(1);
+        }
+    };
+
+    let mut stream = match UnixStream::connect(socket_path()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("No running empitrio instance found: {e}");
+            return Ok(1);
+        }
+    };
+    let line = serde_json::to_string(&command).map_err(io::Error::other)?;
+    writeln!(stream, "{line}")?;
+    Ok(0)
+}
@@ -0,0 +1,99 @@
+// ============================================================================
+// em(π)trio MP3 Player — lint.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Implements the `empitrio lint [<dir>]` subcommand, which reports MP3
+// files with missing ID3 tags as a table (or JSON with `--json`) and can fill in
+// missing titles from the filename with `--fix-title`.
+// Exit code 2 means issues were found, 0 means the directory is clean.
+// ============================================================================
+
+use std::path::PathBuf;
+use std::{env, io};
+
+use crate::{tagread, App, TagIssue, LINT_FIELDS};
+
+/// Run the lint subcommand with the arguments following `lint`.
+/// Returns the process exit code.
+pub fn run(args: &[String]) -> io::Result<i32> {
+    let mut dir: Option<PathBuf> = None;
+    let mut json = false;
+    let mut fix_title = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--fix-title" => fix_title = true,
+            other if other.starts_with("--") => {
+                eprintln!("Unknown lint option: {other}");
+                eprintln!("Usage: empitrio lint [<dir>] [--fix-title] [--json]");
+                return Ok(1);
+            }
+            other => dir = Some(PathBuf::from(other)),
+        }
+    }
+
+    let dir = match dir {
+        Some(dir) => dir,
+        None => env::current_dir()?,
+    };
+
+    let mut issues = App::scan_tag_quality(&dir)?;
+
+    if fix_title {
+        for issue in issues.iter().filter(|i| i.missing_fields.iter().any(|f| f == "TITLE")) {
+            let stem = issue.path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if let Err(e) = tagread::write_title(&issue.path, &stem) {
+                eprintln!("{e}");
+            }
+        }
+        // Report what is still missing after the fix
+        issues = App::scan_tag_quality(&dir)?;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&issues).map_err(io::Error::other)?);
+    } else {
+        print_table(&issues);
+    }
+
+    Ok(if issues.is_empty() { 0 } else { 2 })
+}
+
+/// Print one row per file with an ok/missing status for every checked field
+fn print_table(issues: &[TagIssue]) {
+    if issues.is_empty() {
+        println!("No tag issues found.");
+        return;
+    }
+
+    let names: Vec<String> = issues.iter()
+        .map(|i| i.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default())
+        .collect();
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0).max("FILE".len());
+
+    let mut header = format!("{:<width$}", "FILE");
+    for field in LINT_FIELDS {
+        header.push_str(&format!("  {:<7}", field));
+    }
+    let header = header.trim_end();
+    println!("{header}");
+    println!("{}", "-".repeat(header.chars().count()));
+
+    for (issue, name) in issues.iter().zip(names) {
+        let mut row = format!("{:<width$}", name);
+        for field in LINT_FIELDS {
+            let status = if issue.missing_fields.iter().any(|f| f == field) { "missing" } else { "ok" };
+            row.push_str(&format!("  {:<7}", status));
+        }
+        println!("{}", row.trim_end());
+    }
+
+    println!();
+    println!("{} file(s) with missing tags", issues.len());
+}
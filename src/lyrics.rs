@@ -0,0 +1,62 @@
+// ============================================================================
+// em(π)trio MP3 Player — lyrics.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Synced lyrics from `.lrc` sidecar files (`song.mp3` → `song.lrc`): lines
+// like `[01:23.45]text`, shown in the lyrics panel in time with playback.
+// ============================================================================
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The `.lrc` file next to a track, if there is one
+pub fn lrc_path(track: &Path) -> Option<PathBuf> {
+    ["lrc", "LRC"].iter()
+        .map(|ext| track.with_extension(ext))
+        .find(|path| path.is_file())
+}
+
+/// Timed lines of an `.lrc` file, in playback order. A line may carry several
+/// timestamps (`[00:12.00][01:40.00]chorus`); tags like `[ar:Artist]` and
+/// untimed lines are skipped. Empty if the file can't be read.
+pub fn parse_lrc(path: &Path) -> Vec<(Duration, String)> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some((time, after)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+            match parse_timestamp(time) {
+                Some(time) => times.push(time),
+                None => break,
+            }
+            rest = after;
+        }
+        lines.extend(times.into_iter().map(|time| (time, rest.trim().to_string())));
+    }
+    lines.sort_by_key(|(time, _)| *time);
+    lines
+}
+
+/// Index of the line being sung at `position`: the last one that has started
+pub fn current_line(lines: &[(Duration, String)], position: Duration) -> Option<usize> {
+    lines.partition_point(|(time, _)| *time <= position).checked_sub(1)
+}
+
+/// `mm:ss.xx` (or `mm:ss`) as a duration
+fn parse_timestamp(time: &str) -> Option<Duration> {
+    let (minutes, seconds) = time.split_once(':')?;
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    if !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    Some(Duration::from_secs(minutes * 60) + Duration::from_secs_f64(seconds))
+}
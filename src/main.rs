@@ -11,270 +11,4248 @@
 // handling the terminal UI lifecycle and event loop.
 // ============================================================================
 
-use std::path::PathBuf;
-use std::{env, fs, io};
-use std::sync::mpsc::{Receiver, Sender};
+use std::cmp::Ordering;
+use std::ops::Range;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io::{self, Write}};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 mod player;
-use player::{play_file, toggle_pause, is_paused};
+use player::{PlaybackState, Player, PlayerError, PlayerEvent};
 
+mod analysis;
+mod art;
+mod codec;
+use codec::CodecInfo;
+
+mod cue;
+use cue::{is_cue_file, CueTrack};
+
+mod config;
+use config::{AutoplayMode, Bookmarks, Config, State};
+
+mod eq;
+mod gauge;
+use eq::Equalizer;
+
+mod history;
+#[cfg(unix)]
+mod ipc;
+use history::HistoryEntry;
+mod lint;
+mod lyrics;
+mod meter;
+#[cfg(feature = "mpris")]
+mod mpris;
+mod playlist;
+use playlist::is_playlist_file;
+mod queue;
+mod stream;
+mod benchmark;
+mod fuzzy;
+use fuzzy::{fuzzy_search, FuzzyMatch};
+
+mod tagread;
+use tagread::TrackMeta;
+use image::DynamicImage;
+use indexmap::IndexSet;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
 mod theme;
+use theme::{Theme, ThemeConfig, THEMES};
 mod ui;
 use ui::ui_loop;
 
 use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
-use ratatui::backend::CrosstermBackend;
+use ratatui::backend::{Backend, CrosstermBackend};
 use ratatui::Terminal;
 
+/// Tag fields checked by the `lint` subcommand, as ID3 frame names
+pub const LINT_FIELDS: [&str; 4] = ["TITLE", "ARTIST", "ALBUM", "TRCK"];
+
+/// A file with one or more missing tag fields
+#[derive(Serialize)]
+pub struct TagIssue {
+    pub path: PathBuf,
+    pub missing_fields: Vec<String>,
+}
+
+/// Return true if the path has a playable audio extension
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| player::supported_extensions().iter().any(|s| ext.eq_ignore_ascii_case(s)))
+        .unwrap_or(false)
+}
+
+/// Marks stream URLs in the file list
+pub const STREAM_PREFIX: &str = "🌐 ";
+
+/// True for list entries that are playable tracks (not "...", folders, playlists, cue sheets or streams)
+pub fn is_track_entry(name: &str) -> bool {
+    name != "..."
+        && !name.ends_with('/')
+        && !is_playlist_file(Path::new(name))
+        && !is_cue_file(Path::new(name))
+        && !name.starts_with(STREAM_PREFIX)
+}
+
+/// True for track entries the built-in decoders can play: the ones moving on to the
+/// next or previous track and shuffle pick from
+pub fn is_playable_entry(name: &str) -> bool {
+    is_track_entry(name) && !is_known_unsupported(Path::new(name))
+}
+
+/// True for audio formats that are listed but can't be played with the built-in
+/// decoders (M4A/AAC without the `aac` feature, and Opus, which rodio has no decoder
+/// for), so playing them gives a clear error instead of a decode failure
+pub fn is_known_unsupported(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| (!cfg!(feature = "aac") && ext.eq_ignore_ascii_case("m4a")) || ext.eq_ignore_ascii_case("opus"))
+        .unwrap_or(false)
+}
+
+/// Allowed crossfade lengths, in seconds
+const CROSSFADE_SECS_RANGE: std::ops::RangeInclusive<u64> = 1..=5;
+
+/// Signal levels kept for the visualizer, at 20 per second
+const LEVEL_HISTORY: usize = 200;
+
+/// Number of tracks listed in the queue panel
+const UPCOMING_LIMIT: usize = 20;
+
+/// Volume change per `+`/`-` key press (5%)
+const VOLUME_STEP: f32 = 0.05;
+
+/// Seconds skipped per `←`/`→` key press
+const SEEK_STEP_SECS: u64 = 10;
+
+/// Playback speed change per `<`/`>` key press
+const SPEED_STEP: f32 = 0.1;
+
+/// Allowed playback speeds
+const SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.5..=2.0;
+
+/// Upper bound of progress messages handled per UI tick, so polling never stalls rendering
+const MAX_PROGRESS_MESSAGES_PER_TICK: usize = 10;
+
+/// Largest position step between two progress messages counted as played time;
+/// anything bigger is a seek
+const MAX_PLAYED_PER_PROGRESS_MS: u64 = 1000;
+
+/// Directories with up to this many tracks are measured when sorting by duration;
+/// in bigger ones the sort uses the lengths read in the background so far
+const MAX_TRACKS_MEASURED_FOR_SORT: usize = 200;
+
+/// Image names checked for directory-level album art, in order of preference
+const ALBUM_ART_NAMES: [&str; 6] = [
+    "cover.jpg", "cover.png", "folder.jpg", "folder.png", "albumart.jpg", "albumart.png",
+];
+
+/// Order of the audio files in the file list ("..." and folders always come first)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Name,
+    NameDesc,
+    Duration, // Shortest first
+    Size,     // Smallest first
+    Modified, // Newest first
+    Bpm,
+}
+
+impl SortBy {
+    /// Next sort mode in the `o` cycle
+    pub fn next(self) -> Self {
+        match self {
+            SortBy::Name => SortBy::NameDesc,
+            SortBy::NameDesc => SortBy::Duration,
+            SortBy::Duration => SortBy::Size,
+            SortBy::Size => SortBy::Modified,
+            SortBy::Modified => SortBy::Bpm,
+            SortBy::Bpm => SortBy::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortBy::Name => "name",
+            SortBy::NameDesc => "name (Z-A)",
+            SortBy::Duration => "duration",
+            SortBy::Size => "size",
+            SortBy::Modified => "date modified",
+            SortBy::Bpm => "bpm",
+        }
+    }
+
+    /// Indicator in the file list title, e.g. `↑Name`
+    pub fn indicator(self) -> &'static str {
+        match self {
+            SortBy::Name => "↑Name",
+            SortBy::NameDesc => "↓Name",
+            SortBy::Duration => "↑Duration",
+            SortBy::Size => "↑Size",
+            SortBy::Modified => "↓Modified",
+            SortBy::Bpm => "↑BPM",
+        }
+    }
+}
+
+/// How the text typed after `/` is matched against the file names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Fuzzy, // The text anywhere in the name
+    Regex, // Text starting with `~`: a regular expression
+}
+
+impl SearchMode {
+    /// Shown next to the search input
+    pub fn indicator(self) -> &'static str {
+        match self {
+            SearchMode::Fuzzy => "[search]",
+            SearchMode::Regex => "[~re~]",
+        }
+    }
+}
+
+/// What happens when the end of a track is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatMode {
+    Off, // Stop after the last track
+    One, // Replay the same track
+    All, // Wrap around to the first track
+}
+
+impl RepeatMode {
+    /// Next repeat mode in the `r` cycle
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::One,
+            RepeatMode::One => RepeatMode::All,
+            RepeatMode::All => RepeatMode::Off,
+        }
+    }
+
+    /// Status bar indicator, empty when off
+    pub fn indicator(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "",
+            RepeatMode::One => "[R1] ",
+            RepeatMode::All => "[R∞] ",
+        }
+    }
+}
+
+/// Pane that receives the navigation keys in the two-pane layout (`T`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Left,  // Directory tree
+    Right, // File list
+}
+
+/// Errors kept for the notification history (`N`)
+const MAX_NOTIFICATIONS: usize = 50;
+
+/// Status messages kept for the expanded status bar (`E`)
+const MAX_STATUS_LOG: usize = 50;
+
+/// Most lines the status bar grows to
+const MAX_STATUS_HEIGHT: u16 = 5;
+
+/// How long a new notification replaces the status bar text
+const NOTIFICATION_DURATION: Duration = Duration::from_secs(3);
+
+/// Entries per message while the start directory is read in the background
+const DIR_BATCH_SIZE: usize = 256;
+
+/// Number of directories remembered in `recent_dirs`
+const MAX_RECENT_DIRS: usize = 50;
+
+/// State of the fuzzy directory picker (`Ctrl+G`)
+#[derive(Debug, Clone, Default)]
+pub struct DirPicker {
+    pub query: String,          // Text typed so far
+    pub selected: usize,        // Index into the ranked results
+}
+
+/// State of the output device picker (`d`)
+#[derive(Debug, Clone, Default)]
+pub struct DevicePicker {
+    pub devices: Vec<String>,   // Output device names
+    pub selected: usize,        // Index into `devices`
+}
+
+/// Tags smart playlists are built from (`G`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistField {
+    Genre,
+    Year,
+    Artist,
+}
+
+impl PlaylistField {
+    pub const ALL: [PlaylistField; 3] = [PlaylistField::Genre, PlaylistField::Year, PlaylistField::Artist];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlaylistField::Genre => "Genre",
+            PlaylistField::Year => "Year",
+            PlaylistField::Artist => "Artist",
+        }
+    }
+
+    /// The track's value of this field, if it is tagged
+    fn value(self, meta: &TrackMeta) -> Option<String> {
+        match self {
+            PlaylistField::Genre => meta.genre.clone(),
+            PlaylistField::Year => meta.year.map(|year| year.to_string()),
+            PlaylistField::Artist => meta.artist.clone(),
+        }
+    }
+}
+
+/// State of the smart playlist picker (`G`): first the field, then one of its values
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistPicker {
+    pub field: Option<PlaylistField>, // None while the field is being chosen
+    pub values: Vec<(String, usize)>, // Values of the field with their track counts, once the tags are read
+    pub selected: usize,              // Index into `PlaylistField::ALL`, then into `values`
+}
+
 /// Application state
 pub struct App {
     files: Vec<String>,         // List of .mp3 files in the current directory
     current_dir: PathBuf,       // track current directory
+    initial_dir: PathBuf,       // Directory the app was started in
     selected: usize,            // Index of the currently highlighted/selected file in the list
-    status: String,             // Message shown in the status bar (e.g., "Playing", "Paused")
-    pub current_time: u64,      // Elapsed playback time of the current song, in seconds
-    pub total_time: u64,        // Total duration of the current song, in seconds
+    status_log: VecDeque<String>, // Recent status bar messages, newest last (e.g., "Playing", "Paused")
+    pub status_height: u16,     // Lines of `status_log` shown in the status bar (`E`)
+    pub current_time: u64,      // Elapsed playback time of the current song, in milliseconds
+    pub total_time: u64,        // Total duration of the current song, in milliseconds (0 if unknown)
     pub perc_played: f32,       // Percentage of the current song played (0.0 to 100.0)
     pub songs_played: usize,    // Number of songs played since the app started
-    progress_rx: Option<Receiver<(u64, u64)>>,
+    pub session_time_ms: u64,   // Playback time this session, in milliseconds; seeks don't count
+    pub volume: f32,            // Playback volume (1.0 = 100%)
+    pub speed: f32,             // Playback speed (1.0 = normal, 0.5 to 2.0)
+    pub playing: Option<PathBuf>, // File currently loaded in the player, if any
+    pub command: Option<String>, // Command line input while the `:` prompt is open
+    pub rename_input: Option<String>, // New name (without extension) while renaming the highlighted file (`F2`)
+    pub url_input: Option<String>, // Stream URL being typed in the `u` prompt
+    pub listened: HashSet<String>, // Files in the current directory with a `.listened` marker
+    pub queue: VecDeque<PathBuf>, // Tracks to play next, before falling back to the file list
+    pub crossfade_enabled: bool, // Start the next track early and fade between the two
+    pub crossfade_secs: u64,    // Crossfade length, in seconds (1 to 5)
+    pub gapless_enabled: bool,  // Append the next track to the player before the current one ends
+    pub replay_gain_enabled: bool, // Scale the volume by each track's ReplayGain tag (`:replaygain`)
+    preloaded: Option<PathBuf>, // Track appended for gapless playback, not started yet
+    pub track_ended: bool,      // Set when the current track played to its end
+    track_end_fired: bool,      // Track-end hooks already ran for the current track
+    pub sort_mode: SortBy,      // How audio files are ordered in the list
+    pub meta_cache: HashMap<PathBuf, TrackMeta>, // Tags read so far, keyed by file path
+    pending_intro_skip: Option<u64>, // Intro to skip once the current track reports progress
+    pub show_file_info: bool,   // Show the file info panel for the selected entry (`I`)
+    pub show_track_info: bool,  // Show the technical details of the playing track (`i`)
+    pub show_visualizer: bool,  // Show the level visualizer below the progress bar (`v`)
+    pub show_art: bool,         // Show the cover of the playing track on the right (`a`)
+    current_art: Option<(PathBuf, Option<DynamicImage>)>, // Cover of the playing track, once loaded
+    pub show_lyrics: bool,      // Show the synced lyrics of the playing track on the right (`l`)
+    pub lyrics: Vec<(Duration, String)>, // Timed lines from the playing track's `.lrc` file
+    pub cue_tracks: Vec<CueTrack>, // Tracks of the cue sheet the playing file was opened with; empty otherwise
+    pub cue_index: Option<usize>, // The one of `cue_tracks` playing now
+    pub current_bpm: Option<f32>, // Tempo detected from the start of the playing track
+    pub levels: VecDeque<f32>,  // Recent RMS levels of the playing audio, oldest first
+    level_rx: Option<Receiver<f32>>,
+    pub current_meta: Option<TrackMeta>, // Tags and details of the playing track, once loaded
+    pub read_only: HashSet<String>, // Files in the current directory that are read-only
+    pub total_library_size_cached: Option<u64>, // Bytes of audio under the music dir, once scanned
+    pub total_library_tracks_cached: Option<usize>, // Audio files under the music dir, once scanned
+    pub show_stats: bool,       // Show the stats overlay (`:stats`)
+    pub dirty: bool,            // Something on screen changed since the last frame
+    pub show_hidden_files: bool, // Include dot-files and dot-folders in the list (`Ctrl+H` or `Ctrl+.`)
+    lib_size_rx: Option<Receiver<(u64, usize)>>,
+    pub audio_latency: Option<Duration>, // Measured output latency, subtracted from seek targets
+    latency_rx: Option<Receiver<Option<Duration>>>,
+    pub display_bitrate_in_list: bool, // Show a bitrate badge next to each file (`Ctrl+B`)
+    pub codec_cache: HashMap<PathBuf, CodecInfo>, // Codec headers read so far, keyed by file path
+    pub nav_stack: Vec<PathBuf>, // Directories left behind while navigating, most recent last
+    pub recent_dirs: Vec<PathBuf>, // Recently visited directories, most recent first
+    pub dir_picker: Option<DirPicker>, // Fuzzy directory picker, while open
+    pub recursive_mode: bool,   // List every track below the current directory (`R`)
+    recursive_rx: Option<Receiver<Vec<String>>>,
+    dir_rx: Option<Receiver<Vec<String>>>, // Batches of entries while the start directory is read
+    restore_selected: Option<usize>, // Selection of the last session, applied once the start directory is read
+    pub device_picker: Option<DevicePicker>, // Output device picker, while open
+    pub bookmarks: IndexSet<PathBuf>, // Bookmarked files and folders, oldest first (`b`)
+    pub bookmark_picker: Option<usize>, // Selected bookmark while the bookmark list is open (`B`)
+    pub playlist_picker: Option<PlaylistPicker>, // Smart playlist picker, while open
+    tag_scan_rx: Option<Receiver<Vec<(PathBuf, TrackMeta)>>>, // Tags read for the smart playlist picker
+    pub thumbnail_cache: HashMap<String, Vec<u8>>, // Greyscale album art thumbnails, keyed by file name
+    thumbnail_rx: Option<Receiver<(String, Vec<u8>)>>,
+    pub durations: HashMap<String, u64>, // Track lengths in seconds, keyed by file name
+    durations_rx: Option<Receiver<(String, u64)>>,
+    pub eq: Equalizer,          // Current equalizer settings
+    pub shuffle: bool,          // Play tracks in a random order (`s`)
+    pub play_order: Vec<usize>, // Shuffled indices of the audio files in `files`, while shuffling
+    pub repeat: RepeatMode,     // Off, repeat one or repeat all (`r`)
+    pub history: Vec<HistoryEntry>, // Tracks played, oldest first (persisted if enabled)
+    pub show_history: bool,     // Show the history overlay (`H`)
+    pub show_help: bool,        // Show the key bindings overlay (`?`)
+    pub notifications: VecDeque<(String, Instant)>, // Recent errors, oldest first (at most MAX_NOTIFICATIONS)
+    pub show_notifications: bool, // Show the notification history overlay (`N`)
+    pub show_queue: bool,       // Show the upcoming tracks next to the file list (`Tab`)
+    pub theme_index: usize,     // Index into THEMES of the active theme (`t`)
+    pub list_height: u16,       // Rows of the file list, as last drawn; the Page Up/Down step
+    pub search_query: Option<String>, // Filter typed after `/`, while searching
+    pub filtered_indices: Vec<usize>, // Indices into `files` that match `search_query`
+    pub search_mode: SearchMode, // Regex if `search_query` starts with `~`
+    pub compiled_regex: Option<Regex>, // The regex of a valid `~` query
+    pub search_error: Option<String>, // Why the `~` query isn't a valid regex
+    pub two_pane: bool,         // Directory tree on the left of the file list (`T`)
+    pub pane_focus: Pane,       // Pane the navigation keys act on (`Shift+Tab`)
+    pub dir_tree: Vec<(PathBuf, usize)>, // Tree pane entries: ancestors of the current directory, then its folders, with their depth
+    pub dir_tree_selected: usize, // Index into `dir_tree`
+    pub tree_preview: Option<(PathBuf, Vec<String>)>, // Folder from the tree shown in the file list without entering it
+    pub selection_mode: bool,   // Visual selection mode (`V`)
+    pub selected_files: HashSet<String>, // Entries of `files` picked in selection mode, by name so re-sorting keeps them
+    pub confirm_delete: bool,   // Delete was pressed once in selection mode
+    pub pending_delete: Option<PathBuf>, // File waiting for `y` to confirm its deletion (`Delete`)
+    paused_for_delete: bool,    // Playback was paused while asking to delete the playing track
+    last_jump_char: Option<char>, // Letter of the last jump-to-letter key press
+    last_jump_pos: usize,       // Which of the entries starting with that letter was picked
+    pub loop_a: Option<u64>,    // Start of the A-B loop, in milliseconds (`[`)
+    pub loop_b: Option<u64>,    // End of the A-B loop, in milliseconds (`]`)
+    pub sleep_timer: Option<Instant>, // When playback stops on its own (`S`), if set
+    pub confirm_export_overwrite: bool, // `e` was pressed once and the playlist file already exists
+    last_played: Option<PathBuf>, // Most recently started track, replayed by repeat-one
+    pending_resume: Option<u64>, // Position (ms) to seek to once a restarted track reports progress
+    pub dir_volumes: HashMap<PathBuf, f32>, // Last volume used per directory
+    #[cfg(feature = "mpris")]
+    mpris: Option<mpris::Mpris>, // MPRIS2 service, when the session bus is available
+    #[cfg(unix)]
+    ipc_rx: Option<Receiver<ipc::IpcCommand>>, // Commands from `empitrio --ctl`
+    config: Config,
+    theme_env: ThemeConfig,     // Theme colors from `EMPITRIO_COLOR_*` variables; they win over the config's
+    player: Player,             // Audio output, shared with the playback threads
+    progress_rx: Option<Receiver<PlayerEvent>>,
 }
 
 impl App {
     /// Create new App at current directory, listing folders, mp3 files and "..."
-    pub fn new() -> io::Result<Self> {
-        let current_dir = env::current_dir()?;
-        Self::new_at_dir(current_dir)
-    }
-
-    /// Helper: Create App listing contents of a specific directory
-    pub fn new_at_dir(dir: PathBuf) -> io::Result<Self> {
-        let mut entries = Vec::new();
-
-        // Add "..." entry if we can go up
-        if dir.parent().is_some() {
-            entries.push("...".to_string());
+    /// Opens `startup_dir` from the config if set, else reopens the directory and
+    /// selection of the last session when that directory still exists, else the
+    /// working directory.
+    pub fn new(player: Player) -> io::Result<Self> {
+        if let Some(dir) = Config::load().startup_dir.map(PathBuf::from).filter(|dir| dir.is_dir()) {
+            return Self::new_at_dir(dir, player);
         }
 
-        // List folders (with trailing /) and mp3 files
-        let mut files_and_folders = fs::read_dir(&dir)?
-            .filter_map(|entry| entry.ok())
-            .map(|entry| {
-                let path = entry.path();
-                let name = entry.file_name().to_string_lossy().into_owned();
-
-                if path.is_dir() {
-                    format!("{}/", name)
-                } else if path.extension()
-                    .map(|ext| ext.eq_ignore_ascii_case("mp3"))
-                    .unwrap_or(false)
-                {
-                    name
-                } else {
-                    String::new()
-                }
-            })
-            .filter(|name| !name.is_empty())
-            .collect::<Vec<_>>();
+        let state = State::load();
+        if let Some(dir) = state.last_dir.filter(|dir| dir.is_dir()) {
+            let mut app = Self::new_at_dir(dir, player)?;
+            app.restore_selected = Some(state.last_selected);
+            return Ok(app);
+        }
 
-        // Sort: folders first (with /), then files, both alphabetically
-        files_and_folders.sort_by(|a, b| {
-            let a_is_dir = a.ends_with('/');
-            let b_is_dir = b.ends_with('/');
-            b_is_dir.cmp(&a_is_dir).then(a.to_lowercase().cmp(&b.to_lowercase()))
-        });
+        let current_dir = env::current_dir()?;
+        Self::new_at_dir(current_dir, player)
+    }
 
-        entries.extend(files_and_folders);
+    /// Helper: Create App listing contents of a specific directory. The listing is
+    /// read in the background; see `poll_dir`.
+    pub fn new_at_dir(dir: PathBuf, player: Player) -> io::Result<Self> {
+        let bookmarks = Bookmarks::load();
+        let mut app = Self::with_settings(dir, player, Config::load(), State::load(), bookmarks)?;
+        app.compute_library_size();
+        app.monitor_audio_latency();
+        Ok(app)
+    }
 
-        Ok(Self {
-            files: entries,
+    /// Helper: Create App from settings already in hand. Unlike `new_at_dir` it
+    /// doesn't walk the library or open the audio device to measure latency.
+    fn with_settings(
+        dir: PathBuf,
+        player: Player,
+        config: Config,
+        state: State,
+        bookmarks: Bookmarks,
+    ) -> io::Result<Self> {
+        let mut app = Self {
+            files: Vec::new(),
+            initial_dir: dir.clone(),
             current_dir: dir,
             selected: 0,
-            status: "Press ENTER to play or open folder...".into(),
+            status_log: VecDeque::from(["Press ENTER to play or open folder...".to_string()]),
+            status_height: config.status_bar_lines.clamp(1, MAX_STATUS_HEIGHT),
             current_time: 0,
             total_time: 0,
             perc_played: 0.0,
             songs_played: 0,
+            session_time_ms: 0,
+            volume: config.default_volume,
+            speed: 1.0,
+            playing: None,
+            command: None,
+            rename_input: None,
+            url_input: None,
+            listened: HashSet::new(),
+            queue: VecDeque::new(),
+            crossfade_enabled: config.crossfade_enabled,
+            crossfade_secs: config.crossfade_secs
+                .clamp(*CROSSFADE_SECS_RANGE.start(), *CROSSFADE_SECS_RANGE.end()),
+            gapless_enabled: config.gapless,
+            replay_gain_enabled: config.replay_gain,
+            preloaded: None,
+            track_ended: false,
+            track_end_fired: false,
+            sort_mode: SortBy::Name,
+            meta_cache: HashMap::new(),
+            pending_intro_skip: None,
+            show_file_info: false,
+            show_track_info: false,
+            show_visualizer: false,
+            show_art: false,
+            current_art: None,
+            show_lyrics: false,
+            lyrics: Vec::new(),
+            cue_tracks: Vec::new(),
+            cue_index: None,
+            current_bpm: None,
+            levels: VecDeque::new(),
+            level_rx: None,
+            current_meta: None,
+            read_only: HashSet::new(),
+            total_library_size_cached: None,
+            total_library_tracks_cached: None,
+            show_stats: false,
+            dirty: true,
+            show_hidden_files: false,
+            lib_size_rx: None,
+            audio_latency: None,
+            latency_rx: None,
+            display_bitrate_in_list: config.show_bitrate_column,
+            codec_cache: HashMap::new(),
+            nav_stack: Vec::new(),
+            recent_dirs: state.recent_dirs,
+            dir_picker: None,
+            recursive_mode: false,
+            recursive_rx: None,
+            dir_rx: None,
+            restore_selected: None,
+            device_picker: None,
+            bookmarks: bookmarks.paths.into_iter().collect(),
+            bookmark_picker: None,
+            playlist_picker: None,
+            tag_scan_rx: None,
+            thumbnail_cache: HashMap::new(),
+            thumbnail_rx: None,
+            durations: HashMap::new(),
+            durations_rx: None,
+            eq: Equalizer::default(),
+            shuffle: false,
+            play_order: Vec::new(),
+            repeat: RepeatMode::Off,
+            history: if config.save_history { history::load() } else { Vec::new() },
+            show_history: false,
+            show_help: false,
+            notifications: VecDeque::new(),
+            show_notifications: false,
+            show_queue: false,
+            theme_index: 0,
+            list_height: 0,
+            search_query: None,
+            filtered_indices: Vec::new(),
+            search_mode: SearchMode::Fuzzy,
+            compiled_regex: None,
+            search_error: None,
+            two_pane: false,
+            pane_focus: Pane::Right,
+            dir_tree: Vec::new(),
+            dir_tree_selected: 0,
+            tree_preview: None,
+            selection_mode: false,
+            selected_files: HashSet::new(),
+            confirm_delete: false,
+            pending_delete: None,
+            paused_for_delete: false,
+            last_jump_char: None,
+            last_jump_pos: 0,
+            loop_a: None,
+            loop_b: None,
+            sleep_timer: None,
+            confirm_export_overwrite: false,
+            last_played: state.last_played,
+            pending_resume: None,
+            dir_volumes: state.dir_volumes,
+            #[cfg(feature = "mpris")]
+            mpris: None,
+            #[cfg(unix)]
+            ipc_rx: None,
+            config,
+            theme_env: ThemeConfig::from_env(),
+            player,
             progress_rx: None,
-        })
-    }
+        };
 
-    pub fn next(&mut self) {
-        if !self.files.is_empty() {
-            self.selected = (self.selected + 1) % self.files.len();
+        app.player.set_volume(app.volume);
+        app.player.set_gapless(app.gapless_enabled);
+        app.player.set_replay_gain(app.replay_gain_enabled);
+        app.meter_levels();
+        app.remember_volume_per_directory();
+        app.load_dir_in_background()?;
+
+        let invalid_colors = app.theme_overrides().invalid_fields();
+        if !invalid_colors.is_empty() {
+            app.set_status(format!("Warning: theme colors outside 0-255 ignored: {}", invalid_colors.join(", ")));
+        }
+        if let Some(e) = app.config.load_error() {
+            let message = format!("Error: config.toml {}; using defaults", e);
+            app.push_notification(&message);
         }
+
+        Ok(app)
     }
 
-    pub fn previous(&mut self) {
-        if !self.files.is_empty() {
-            if self.selected == 0 {
-                self.selected = self.files.len() - 1;
-            } else {
-                self.selected -= 1;
-            }
-        }
+    /// List folders, mp3 files and "..." of a directory, sorted for display.
+    /// Dot-files and dot-folders are skipped unless `show_hidden` is set.
+    fn read_entries(dir: &Path, show_hidden: bool) -> io::Result<Vec<String>> {
+        let entries = Self::read_entries_keyed(dir, show_hidden)?;
+        Ok(entries.into_iter().map(|(name, _)| name).collect())
     }
 
-    /// Advances selected to next MP3 file only, skipping folders and "...".
-    /// Returns true if advanced to a different mp3, false if no next mp3 found or only one mp3 exists.
-    pub fn next_mp3(&mut self) -> bool {
-        if self.files.is_empty() {
-            return false;
+    /// `read_entries` with the sort key of each entry, for merging into the list
+    fn read_entries_keyed(dir: &Path, show_hidden: bool) -> io::Result<Vec<(String, NameKey)>> {
+        // Add "..." entry if we can go up
+        let up = dir.parent().map(|_| "...".to_string());
+
+        // List folders (with trailing /) and mp3 files
+        let mut entries = up.into_iter()
+            .chain(fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| Self::list_entry(&entry, show_hidden)))
+            .map(|name| {
+                let key = name_sort_key(&name);
+                (name, key)
+            })
+            .collect::<Vec<_>>();
+
+        // Sort: "..." first, then folders (with /), then files, both alphabetically
+        entries.sort_by(|(_, a), (_, b)| a.cmp(b));
+        Ok(entries)
+    }
+
+    /// List name of a directory entry: folders get a trailing `/`, other files are
+    /// only listed if they are tracks or playlists
+    fn list_entry(entry: &fs::DirEntry, show_hidden: bool) -> Option<String> {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !show_hidden && name.starts_with('.') {
+            return None;
         }
 
-        // Count how many mp3 files exist (exclude "..." and folders)
-        let mp3_count = self.files.iter()
-            .filter(|f| **f != "..." && !f.ends_with('/'))
-            .count();
+        let path = entry.path();
+        if path.is_dir() {
+            Some(format!("{}/", name))
+        } else if is_audio_file(&path) || is_known_unsupported(&path) || is_playlist_file(&path) || is_cue_file(&path) {
+            Some(name)
+        } else {
+            None
+        }
+    }
 
-        // If only one mp3 file exists, don't advance
-        if mp3_count <= 1 {
-            return false;
+    /// Read the start directory in a background thread, so a huge or slow (network)
+    /// directory doesn't hold up the first frame. Entries arrive in batches through
+    /// `poll_dir`; until then the list only holds "...".
+    fn load_dir_in_background(&mut self) -> io::Result<()> {
+        let entries = fs::read_dir(&self.current_dir)?;
+        let show_hidden = self.show_hidden_files;
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.dir_rx = Some(rx);
+        self.files = if self.current_dir.parent().is_some() { vec!["...".to_string()] } else { Vec::new() };
+        // Shuffled again once the listing is complete
+        self.play_order.clear();
+        self.set_status("Loading…".into());
+
+        std::thread::spawn(move || {
+            let mut batch = Vec::new();
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                batch.extend(Self::list_entry(&entry, show_hidden));
+                if batch.len() == DIR_BATCH_SIZE && tx.send(std::mem::take(&mut batch)).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(batch);
+        });
+        Ok(())
+    }
+
+    /// Add the entries read by `load_dir_in_background` since the last call, keeping
+    /// the list in the current sort order and the selection on its entry
+    pub fn poll_dir(&mut self) {
+        let Some(rx) = &self.dir_rx else {
+            return;
+        };
+        let mut batches = Vec::new();
+        let done = loop {
+            match rx.try_recv() {
+                Ok(batch) => batches.push(batch),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+
+        self.dirty |= done || !batches.is_empty();
+        if !batches.is_empty() {
+            self.files.extend(batches.into_iter().flatten());
+            self.apply_sort();
+        }
+        if done {
+            self.finish_dir_load();
         }
+    }
 
-        let start_index = self.selected;
-        let mut next_index = (start_index + 1) % self.files.len();
+    /// Block until the background directory read is complete. Used where the whole
+    /// listing is needed right away, e.g. to autoplay its first track.
+    pub fn wait_for_dir(&mut self) {
+        let Some(rx) = self.dir_rx.take() else {
+            return;
+        };
+        self.files.extend(rx.iter().flatten());
+        self.finish_dir_load();
+    }
 
-        while (self.files[next_index] == "..." || self.files[next_index].ends_with('/')) && next_index != start_index {
-            next_index = (next_index + 1) % self.files.len();
+    /// The background directory read is done: refresh everything derived from the listing
+    fn finish_dir_load(&mut self) {
+        self.dir_rx = None;
+        let selected_name = self.files.get(self.selected).cloned();
+        let files = std::mem::take(&mut self.files);
+        self.set_listing(files);
+
+        // The selection restored from the last session is only valid in the full listing
+        let restored = self.restore_selected.take();
+        self.selected = match restored {
+            Some(index) => index.min(self.files.len().saturating_sub(1)),
+            None => selected_name.and_then(|name| self.files.iter().position(|f| *f == name)).unwrap_or(0),
+        };
+        if self.status() == "Loading…" {
+            self.set_status("Press ENTER to play or open folder...".into());
         }
+    }
 
-        if next_index == start_index {
-            // No next mp3 found (shouldn't happen if mp3_count > 1, but safe check)
-            false
+    /// Switch to another directory: refresh the listing and reset the selection.
+    /// In recursive mode the listing arrives later from a background scan.
+    fn enter_dir(&mut self, dir: PathBuf) -> io::Result<()> {
+        let files = if self.recursive_mode {
+            Vec::new()
         } else {
-            self.selected = next_index;
-            true
+            Self::read_entries(&dir, self.show_hidden_files)?
+        };
+        self.dir_rx = None;
+        self.restore_selected = None;
+        self.current_dir = dir;
+        self.remember_volume_per_directory();
+        self.set_listing(files);
+        self.tree_preview = None;
+        if self.two_pane {
+            self.refresh_dir_tree();
         }
+        if self.recursive_mode {
+            self.scan_recursive_in_background();
+        }
+        Ok(())
     }
 
-    /// Open folder, go up, or play file based on selection
-    pub fn open_selected(&mut self, progress_tx: &Sender<(u64, u64)>) -> io::Result<()> {
-        if self.files.is_empty() {
-            self.status = "No files or folders found".into();
-            return Ok(());
+    /// Replace the entries of the list and refresh everything derived from them
+    fn set_listing(&mut self, files: Vec<String>) {
+        self.files = files;
+        self.selected = 0;
+        self.durations.clear();
+        self.close_search();
+        self.exit_selection_mode();
+        // Also shuffles the new listing when shuffle is on
+        self.apply_sort();
+        self.refresh_listened();
+        self.refresh_read_only();
+        self.refresh_codec_info();
+        self.generate_thumbnails_in_background();
+        self.scan_missing_durations();
+    }
+
+    /// Switch between the directory view and a flat list of every track below it (`R`)
+    pub fn toggle_recursive(&mut self) {
+        self.recursive_mode = !self.recursive_mode;
+        self.recursive_rx = None;
+        if let Err(e) = self.enter_dir(self.current_dir.clone()) {
+            self.push_notification(&format!("Error: {}", e));
+            return;
+        }
+        if !self.recursive_mode {
+            self.set_status("Directory view".into());
         }
+    }
 
-        let selection = &self.files[self.selected];
+    /// List the audio files of the whole tree below the current directory, as paths
+    /// relative to it, in a background thread. Results arrive through `poll_recursive_scan`.
+    fn scan_recursive_in_background(&mut self) {
+        let root = self.current_dir.clone();
+        let show_hidden = self.show_hidden_files;
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.recursive_rx = Some(rx);
+        self.set_status("Scanning subfolders...".into());
 
-        if selection == "..." {
-            // Go up one directory if possible
-            if let Some(parent) = self.current_dir.parent() {
-                self.current_dir = parent.to_path_buf();
+        std::thread::spawn(move || {
+            let mut files: Vec<String> = walkdir::WalkDir::new(&root)
+                .into_iter()
+                .filter_entry(|entry| entry.depth() == 0 || show_hidden || !entry.file_name().to_string_lossy().starts_with('.'))
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| {
+                    let path = entry.path();
+                    entry.file_type().is_file() && (is_audio_file(path) || is_known_unsupported(path))
+                })
+                .filter_map(|entry| Some(entry.path().strip_prefix(&root).ok()?.to_string_lossy().into_owned()))
+                .collect();
+            files.sort_by(|a, b| compare_by_name(a, b));
+            let _ = tx.send(files);
+        });
+    }
 
-                // Only update directory listing and reset selected index
-                let new_app = App::new_at_dir(self.current_dir.clone())?;
-                self.files = new_app.files;
-                self.selected = 0;
+    /// Show the result of the recursive scan once it is done
+    pub fn poll_recursive_scan(&mut self) {
+        let Some(files) = self.recursive_rx.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return;
+        };
+        self.recursive_rx = None;
+        self.dirty = true;
+        self.set_status(format!("Recursive view: {} tracks", files.len()));
+        self.set_listing(files);
+    }
 
-                self.status = format!("Moved up to {:?}", self.current_dir);
+    /// Build album art thumbnails for every audio file of the current directory in a
+    /// background thread: embedded art first, the directory's cover image otherwise.
+    /// Results arrive through `poll_thumbnails`. Only runs while album art is enabled.
+    pub fn generate_thumbnails_in_background(&mut self) {
+        self.thumbnail_cache.clear();
+        self.thumbnail_rx = None;
+        self.generate_missing_thumbnails();
+    }
 
-            } else {
-                self.status = "Already at root directory".into();
+    /// Build thumbnails for the listed tracks that don't have one yet
+    fn generate_missing_thumbnails(&mut self) {
+        if !self.config.show_album_art {
+            return;
+        }
+
+        let files: Vec<(String, PathBuf)> = self.files.iter()
+            .filter(|f| is_track_entry(f) && !self.thumbnail_cache.contains_key(*f))
+            .map(|f| (f.clone(), self.current_dir.join(f)))
+            .collect();
+        let dir_art = Self::detect_album_art_file(&self.current_dir);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.thumbnail_rx = Some(rx);
+
+        std::thread::spawn(move || {
+            // The directory image is shared by all files without embedded art
+            let dir_thumbnail = dir_art
+                .and_then(|path| fs::read(path).ok())
+                .and_then(|data| art::make_thumbnail(&data));
+
+            for (name, path) in files {
+                let thumbnail = tagread::read_cover_art(&path)
+                    .and_then(|data| art::make_thumbnail(&data))
+                    .or_else(|| dir_thumbnail.clone());
+
+                if let Some(pixels) = thumbnail {
+                    // The receiver is gone once the user left the directory
+                    if tx.send((name, pixels)).is_err() {
+                        return;
+                    }
+                }
             }
-        } else if selection.ends_with('/') {
-            // Enter folder
-            let folder_name = selection.trim_end_matches('/');
-            let new_path = self.current_dir.join(folder_name);
-            if new_path.is_dir() {
-                self.current_dir = new_path;
+        });
+    }
+
+    /// Move finished thumbnails from the background thread into `thumbnail_cache`,
+    /// and forget the thread once it is done
+    pub fn poll_thumbnails(&mut self) {
+        let Some(rx) = &self.thumbnail_rx else {
+            return;
+        };
+        loop {
+            match rx.try_recv() {
+                Ok((name, pixels)) => {
+                    self.thumbnail_cache.insert(name, pixels);
+                    self.dirty = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.thumbnail_rx = None;
+                    self.dirty = true;
+                    break;
+                }
+            }
+        }
+    }
 
-                // Only update directory listing and reset selected index
-                let new_app = App::new_at_dir(self.current_dir.clone())?;
-                self.files = new_app.files;
-                self.selected = 0;
+    /// True while the background thread is still building thumbnails
+    pub fn thumbnails_loading(&self) -> bool {
+        self.thumbnail_rx.is_some()
+    }
 
-                self.status = format!("Entered folder {:?}", self.current_dir);
+    /// Read the length of the listed tracks whose length isn't known yet in a
+    /// background thread. Results arrive through `poll_durations`.
+    fn scan_missing_durations(&mut self) {
+        let files: Vec<(String, PathBuf)> = self.files.iter()
+            .filter(|f| is_track_entry(f) && !self.durations.contains_key(*f))
+            .map(|f| (f.clone(), self.current_dir.join(f)))
+            .collect();
 
-            } else {
-                self.status = format!("Folder not found: {}", folder_name);
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.durations_rx = Some(rx);
+
+        std::thread::spawn(move || {
+            for (name, path) in files {
+                if let Some(duration) = player::track_duration(&path) {
+                    // The receiver is gone once the user left the directory
+                    if tx.send((name, duration.as_secs())).is_err() {
+                        return;
+                    }
+                }
             }
-        } else {
-            // Play file
-            let file_path = self.current_dir.join(selection);
-            self.status = format!("  Playing: {}", selection);
-            let _ = play_file(file_path.to_string_lossy().as_ref(), progress_tx.clone());
+        });
+    }
+
+    /// Move finished durations from the background thread into `durations`
+    pub fn poll_durations(&mut self) {
+        if let Some(rx) = &self.durations_rx {
+            let before = self.durations.len();
+            self.durations.extend(rx.try_iter());
+            self.dirty |= self.durations.len() != before;
+        }
+    }
+
+    /// Read the codec headers of the listed files while the bitrate column is shown
+    fn refresh_codec_info(&mut self) {
+        if !self.display_bitrate_in_list {
+            return;
+        }
+        for name in self.files.iter().filter(|f| is_track_entry(f)) {
+            let path = self.current_dir.join(name);
+            self.codec_cache
+                .entry(path)
+                .or_insert_with_key(|path| codec::read_codec_info(path));
         }
+    }
+
+    /// Bitrate badge (`[320k]`, `[VBR]`, `[FLAC]`, `[M4A?]`) of a listed file, if known
+    pub fn bitrate_badge(&self, name: &str) -> Option<String> {
+        self.codec_cache.get(&self.current_dir.join(name))?.badge()
+    }
+
+    /// Toggle the bitrate column in the file list (`Ctrl+B`)
+    pub fn toggle_bitrate_column(&mut self) {
+        self.display_bitrate_in_list = !self.display_bitrate_in_list;
+        self.refresh_codec_info();
+    }
+
+    /// Navigate to another directory, remembering the current one in `nav_stack`
+    /// and the new one in `recent_dirs`
+    fn change_dir(&mut self, dir: PathBuf) -> io::Result<()> {
+        let previous = self.current_dir.clone();
+        self.enter_dir(dir)?;
 
+        self.nav_stack.push(previous);
+        self.recent_dirs.retain(|d| *d != self.current_dir);
+        self.recent_dirs.insert(0, self.current_dir.clone());
+        self.recent_dirs.truncate(MAX_RECENT_DIRS);
         Ok(())
     }
 
+    /// Show or hide the directory tree on the left of the file list (`T`)
+    pub fn toggle_two_pane(&mut self) {
+        self.two_pane = !self.two_pane;
+        self.pane_focus = Pane::Right;
+        self.tree_preview = None;
+        if self.two_pane {
+            self.refresh_dir_tree();
+        }
+    }
 
-    /// Convenience: Call open_selected and update status if error
-    pub fn select(&mut self, progress_tx: &Sender<(u64, u64)>) {
-        if let Err(e) = self.open_selected(progress_tx) {
-            self.status = format!("Error: {}", e);
+    /// Move the navigation keys to the other pane (`Shift+Tab`). Back in the file
+    /// list, a folder previewed from the tree is replaced by the current directory.
+    pub fn switch_pane(&mut self) {
+        if !self.two_pane {
+            return;
+        }
+        self.pane_focus = match self.pane_focus {
+            Pane::Left => Pane::Right,
+            Pane::Right => Pane::Left,
+        };
+        if self.pane_focus == Pane::Right {
+            self.tree_preview = None;
         }
     }
 
-    pub fn set_progress_receiver(&mut self, rx: Receiver<(u64, u64)>) {
-        self.progress_rx = Some(rx);
+    /// True while the navigation keys act on the directory tree
+    pub fn tree_focused(&self) -> bool {
+        self.two_pane && self.pane_focus == Pane::Left
     }
 
-    pub fn poll_progress(&mut self) {
-        if let Some(rx) = &self.progress_rx {
-            while let Ok((elapsed, total)) = rx.try_recv() {
-                if total > 0 && elapsed >= total {
-                    // Reset progress when the song finishes
-                    self.current_time = 0;
-                    self.total_time = 0;
-                    self.perc_played = 0.0;
+    /// Rebuild the tree pane: every ancestor of the current directory from the root
+    /// down, then the folders inside it one level deeper
+    fn refresh_dir_tree(&mut self) {
+        let mut tree: Vec<(PathBuf, usize)> = self.current_dir.ancestors()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .enumerate()
+            .map(|(depth, dir)| (dir.to_path_buf(), depth))
+            .collect();
+        self.dir_tree_selected = tree.len() - 1;
 
-                    // Update status to indicate playback finished or stopped
-                    self.status = "Playback finished".into();
-                } else {
-                    self.current_time = elapsed;
-                    self.total_time = total;
-                    self.perc_played = if total > 0 {
-                        (elapsed as f32 / total as f32) * 100.0
-                    } else {
-                        0.0
-                    };
-                }
-            }
+        let depth = tree.len();
+        let folders = Self::read_entries(&self.current_dir, self.show_hidden_files).unwrap_or_default();
+        tree.extend(folders.iter()
+            .filter(|name| name.ends_with('/'))
+            .map(|name| (self.current_dir.join(name.trim_end_matches('/')), depth)));
+        self.dir_tree = tree;
+    }
+
+    pub fn tree_next(&mut self) {
+        if !self.dir_tree.is_empty() {
+            self.dir_tree_selected = (self.dir_tree_selected + 1) % self.dir_tree.len();
         }
     }
 
-    pub fn pause(&mut self) {
-        toggle_pause();
+    pub fn tree_previous(&mut self) {
+        if !self.dir_tree.is_empty() {
+            self.dir_tree_selected = self.dir_tree_selected.checked_sub(1).unwrap_or(self.dir_tree.len() - 1);
+        }
+    }
 
-        if is_paused() {
-            self.status = "  PAUSED".into();
-        } else {
-            if let Some(filename) = self.files.get(self.selected) {
-                self.status = format!("  Playing: {}", filename);
-            } else {
-                self.status.clear();
-            }
+    /// `Enter` in the tree pane: preview the highlighted folder in the file list,
+    /// or enter it when it is already previewed (or is the current directory)
+    pub fn tree_select(&mut self) {
+        if let Err(e) = self.open_tree_entry() {
+            self.push_notification(&format!("Error: {}", e));
         }
     }
-}
 
-fn main() -> io::Result<()> {
-    // Create a channel for playback progress (elapsed_secs, total_secs)
-    let (progress_tx, progress_rx) = std::sync::mpsc::channel::<(u64, u64)>();
+    fn open_tree_entry(&mut self) -> io::Result<()> {
+        let Some((dir, _)) = self.dir_tree.get(self.dir_tree_selected).cloned() else {
+            return Ok(());
+        };
 
-    // Set up terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+        let previewed = self.tree_preview.as_ref().is_some_and(|(preview, _)| *preview == dir);
+        if previewed || dir == self.current_dir {
+            self.tree_preview = None;
+            if dir != self.current_dir {
+                self.change_dir(dir)?;
+                self.set_status(format!("Entered folder {:?}", self.current_dir));
+            }
+            self.pane_focus = Pane::Right;
+        } else {
+            let entries = Self::read_entries(&dir, self.show_hidden_files)?
+                .into_iter()
+                .filter(|name| name != "...")
+                .collect();
+            self.set_status(format!("Previewing {:?}, press Enter again to open it", dir));
+            self.tree_preview = Some((dir, entries));
+        }
+        Ok(())
+    }
 
-    // Initialize app and give it the receiver side of the channel
-    let mut app = App::new()?;
-    app.set_progress_receiver(progress_rx);
+    /// Open the fuzzy directory picker (`Ctrl+G`)
+    pub fn open_dir_picker(&mut self) {
+        self.dir_picker = Some(DirPicker::default());
+    }
 
-    // Run the UI loop passing terminal, app, and the sender
-    let result = ui_loop(&mut terminal, &mut app, progress_tx);
+    /// Open the output device picker (`d`)
+    pub fn open_device_picker(&mut self) {
+        let devices = player::list_output_devices();
+        if devices.is_empty() {
+            self.set_status("No output devices found".into());
+            return;
+        }
+        self.device_picker = Some(DevicePicker { devices, selected: 0 });
+    }
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    /// Path of a listed entry; folders are listed with a trailing `/`
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.current_dir.join(name.trim_end_matches('/'))
+    }
+
+    /// Whether a listed entry is bookmarked, for the `★` marker
+    pub fn is_bookmarked(&self, name: &str) -> bool {
+        !self.bookmarks.is_empty() && self.bookmarks.contains(&self.entry_path(name))
+    }
+
+    /// Bookmark the highlighted file or folder, or remove its bookmark (`b`)
+    pub fn toggle_bookmark(&mut self) {
+        let Some(name) = self.files.get(self.selected).filter(|f| *f != "...") else {
+            return;
+        };
+        let path = self.entry_path(name);
+        let label = dir_basename(&path);
+        if self.bookmarks.shift_remove(&path) {
+            self.set_status(format!("Removed bookmark: {}", label));
+        } else {
+            self.bookmarks.insert(path);
+            self.set_status(format!("Bookmarked: {}", label));
+        }
+
+        let bookmarks = Bookmarks { paths: self.bookmarks.iter().cloned().collect() };
+        if let Err(e) = bookmarks.save() {
+            self.push_notification(&format!("Error: {}", e));
+        }
+    }
+
+    /// Open the bookmark list (`B`)
+    pub fn open_bookmarks(&mut self) {
+        if self.bookmarks.is_empty() {
+            self.set_status("No bookmarks yet (b to add one)".into());
+            return;
+        }
+        self.bookmark_picker = Some(0);
+    }
+
+    /// Go to the bookmark selected in the list and close it: into a folder, or to
+    /// the folder of a file with the file highlighted
+    pub fn jump_to_bookmark(&mut self) {
+        let Some(path) = self.bookmark_picker.take().and_then(|i| self.bookmarks.get_index(i)).cloned() else {
+            return;
+        };
+
+        let (dir, file) = if path.is_dir() {
+            (path.clone(), None)
+        } else {
+            match path.parent() {
+                Some(parent) if path.exists() => (parent.to_path_buf(), path.file_name()),
+                _ => {
+                    self.set_status(format!("Bookmark no longer exists: {}", path.display()));
+                    return;
+                }
+            }
+        };
+
+        if dir != self.current_dir {
+            if let Err(e) = self.change_dir(dir) {
+                self.push_notification(&format!("Error: {}", e));
+                return;
+            }
+        }
+        if let Some(file) = file {
+            let name = file.to_string_lossy();
+            if let Some(index) = self.files.iter().position(|f| *f == name) {
+                self.selected = index;
+            }
+        }
+        self.set_status(format!("Jumped to bookmark: {}", dir_basename(&path)));
+    }
+
+    /// Move the selection back to the playing track (`c`), first changing to its
+    /// folder when it isn't listed in the current one
+    pub fn go_to_playing(&mut self) {
+        let Some(path) = self.playing.clone() else {
+            self.set_status("Nothing is playing".into());
+            return;
+        };
+
+        let entry = if self.is_streaming() {
+            format!("{}{}", STREAM_PREFIX, path.display())
+        } else {
+            // In recursive mode tracks below the current directory are listed here already
+            let listed = path.strip_prefix(&self.current_dir).ok()
+                .map(|name| name.to_string_lossy().into_owned())
+                .filter(|name| self.files.contains(name));
+            match (listed, path.parent()) {
+                (Some(name), _) => name,
+                (None, Some(parent)) => {
+                    if let Err(e) = self.change_dir(parent.to_path_buf()) {
+                        self.push_notification(&format!("Error: {}", e));
+                        return;
+                    }
+                    path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+                }
+                (None, None) => String::new(),
+            }
+        };
+
+        match self.files.iter().position(|f| *f == entry) {
+            Some(index) => {
+                self.selected = index;
+                let label = self.track_label(&path);
+                self.set_status(format!("Now playing: {}", label));
+            }
+            None => self.set_status(format!("Playing track not found in {}", self.current_dir.display())),
+        }
+    }
+
+    /// Switch to the device selected in the picker and close it; the current
+    /// track continues on the new device from the same position
+    pub fn select_output_device(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        let Some(picker) = self.device_picker.take() else {
+            return;
+        };
+        let Some(name) = picker.devices.get(picker.selected) else {
+            return;
+        };
+
+        self.player.set_preferred_device(name);
+        self.seek_to_current_offset(progress_tx);
+        self.set_status(format!("Output device: {}", name));
+    }
+
+    /// Open the smart playlist picker (`G`)
+    pub fn open_playlist_picker(&mut self) {
+        self.playlist_picker = Some(PlaylistPicker::default());
+    }
+
+    /// Close the smart playlist picker; a tag scan still running is dropped
+    pub fn close_playlist_picker(&mut self) {
+        self.playlist_picker = None;
+        self.tag_scan_rx = None;
+    }
+
+    /// Enter in the smart playlist picker: choosing a field reads the tags of the
+    /// listed tracks in the background, choosing a value queues its tracks
+    pub fn select_in_playlist_picker(&mut self) {
+        let Some(picker) = self.playlist_picker.as_mut() else {
+            return;
+        };
+        match picker.field {
+            None => {
+                picker.field = Some(PlaylistField::ALL[picker.selected]);
+                picker.selected = 0;
+                self.scan_tags_in_background();
+            }
+            Some(field) => {
+                let Some((value, _)) = picker.values.get(picker.selected).cloned() else {
+                    return;
+                };
+                let tracks = self.generate_playlist_by(field, &value);
+                self.set_status(format!("Queued {} tracks: {} {}", tracks.len(), field.label(), value));
+                self.queue.extend(tracks);
+                self.close_playlist_picker();
+            }
+        }
+    }
+
+    /// Read the tags of the listed tracks that aren't in `meta_cache` yet in a
+    /// background thread; they arrive through `poll_tag_scan`. In recursive mode
+    /// that covers the whole tree.
+    fn scan_tags_in_background(&mut self) {
+        let missing: Vec<PathBuf> = self.listed_tracks().into_iter()
+            .filter(|path| !self.meta_cache.contains_key(path))
+            .collect();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.tag_scan_rx = Some(rx);
+        self.set_status("Reading tags...".into());
+
+        std::thread::spawn(move || {
+            let tags = missing.into_iter()
+                .map(|path| {
+                    let meta = tagread::read_tags(&path);
+                    (path, meta)
+                })
+                .collect();
+            let _ = tx.send(tags);
+        });
+    }
+
+    /// Cache the tags read by `scan_tags_in_background` and list the values of the
+    /// chosen field in the picker
+    pub fn poll_tag_scan(&mut self) {
+        let Some(tags) = self.tag_scan_rx.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return;
+        };
+        self.tag_scan_rx = None;
+        self.dirty = true;
+        self.meta_cache.extend(tags);
+
+        let Some(field) = self.playlist_picker.as_ref().and_then(|picker| picker.field) else {
+            return;
+        };
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for path in self.listed_tracks() {
+            if let Some(value) = self.meta_cache.get(&path).and_then(|meta| field.value(meta)) {
+                *counts.entry(value).or_default() += 1;
+            }
+        }
+        if counts.is_empty() {
+            self.set_status(format!("No tracks here have a {} tag", field.label().to_lowercase()));
+            self.close_playlist_picker();
+            return;
+        }
+
+        let mut values: Vec<(String, usize)> = counts.into_iter().collect();
+        values.sort_by_key(|(value, _)| value.to_lowercase());
+        self.set_status(format!("{} {} values", values.len(), field.label().to_lowercase()));
+        if let Some(picker) = self.playlist_picker.as_mut() {
+            picker.values = values;
+        }
+    }
+
+    /// The listed tracks whose `field` tag is `value`, sorted by file name.
+    /// Only tags already in `meta_cache` are considered.
+    pub fn generate_playlist_by(&self, field: PlaylistField, value: &str) -> Vec<PathBuf> {
+        let mut tracks: Vec<PathBuf> = self.listed_tracks().into_iter()
+            .filter(|path| {
+                self.meta_cache.get(path)
+                    .and_then(|meta| field.value(meta))
+                    .is_some_and(|v| v == value)
+            })
+            .collect();
+        tracks.sort_by_key(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .unwrap_or_default()
+        });
+        tracks
+    }
+
+    /// Full paths of the tracks in the file list
+    fn listed_tracks(&self) -> Vec<PathBuf> {
+        self.files.iter()
+            .filter(|f| is_track_entry(f))
+            .map(|f| self.current_dir.join(f))
+            .collect()
+    }
+
+    /// Directories from `nav_stack` and `recent_dirs`, ranked by how well their
+    /// basename matches `query`. Ties keep the most recent first.
+    pub fn dir_picker_results(&self, query: &str) -> Vec<(PathBuf, FuzzyMatch)> {
+        let mut candidates: Vec<PathBuf> = Vec::new();
+        for dir in self.recent_dirs.iter().chain(self.nav_stack.iter().rev()) {
+            if !candidates.contains(dir) {
+                candidates.push(dir.clone());
+            }
+        }
+
+        let mut results: Vec<(PathBuf, FuzzyMatch)> = candidates.into_iter()
+            .filter_map(|dir| {
+                let found = fuzzy_search(query, &dir_basename(&dir))?;
+                Some((dir, found))
+            })
+            .collect();
+        results.sort_by_key(|(_, found)| std::cmp::Reverse(found.score));
+        results
+    }
+
+    /// Jump to the directory selected in the picker and close it
+    pub fn fuzzy_dir_jump(&mut self) {
+        let Some(picker) = self.dir_picker.take() else {
+            return;
+        };
+        let Some((dir, _)) = self.dir_picker_results(&picker.query).into_iter().nth(picker.selected) else {
+            return;
+        };
+
+        match self.change_dir(dir) {
+            Ok(()) => self.set_status(format!("Jumped to {:?}", self.current_dir)),
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Re-read the current directory and apply only what changed: entries that are
+    /// gone are dropped and new ones are merged in at their place, so the selection,
+    /// the search and what is known about the other entries survive. Only the plain
+    /// name order can be merged into; other orders and the recursive view are re-listed.
+    ///
+    /// The first listing of a directory doesn't come through here, as there is nothing
+    /// to merge into: `enter_dir` lists it from scratch, and `load_dir_in_background`
+    /// streams in the start directory so a huge one doesn't hold up the first frame.
+    pub fn refresh_files(&mut self) -> io::Result<()> {
+        if self.recursive_mode || self.sort_mode != SortBy::Name || self.config.sink_listened {
+            return self.relist_dir();
+        }
+
+        let fresh = Self::read_entries_keyed(&self.current_dir, self.show_hidden_files)?;
+        let selected_name = self.files.get(self.selected).cloned();
+
+        // Both lists are in name order, so one pass finds the additions and removals.
+        // Each entry's sort key is built once, not on every comparison.
+        let mut merged = Vec::with_capacity(fresh.len());
+        let (mut added, mut removed) = (false, false);
+        let mut old = std::mem::take(&mut self.files).into_iter()
+            .map(|name| {
+                let key = name_sort_key(&name);
+                (name, key)
+            })
+            .peekable();
+        let mut new = fresh.into_iter().peekable();
+        loop {
+            let order = match (old.peek(), new.peek()) {
+                (Some((_, a)), Some((_, b))) => a.cmp(b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match order {
+                // Streams aren't on disk; they stay listed until the directory changes
+                Ordering::Less => match old.next() {
+                    Some((entry, _)) if entry.starts_with(STREAM_PREFIX) => merged.push(entry),
+                    _ => removed = true,
+                },
+                Ordering::Greater => {
+                    merged.extend(new.next().map(|(name, _)| name));
+                    added = true;
+                }
+                Ordering::Equal => {
+                    old.next();
+                    merged.extend(new.next().map(|(name, _)| name));
+                }
+            }
+        }
+        self.files = merged;
+        if !added && !removed {
+            return Ok(());
+        }
+
+        // The same entry stays selected; in place of a removed one, its neighbour
+        let last = self.files.len().saturating_sub(1);
+        self.selected = selected_name
+            .and_then(|name| self.files.iter().position(|f| *f == name))
+            .unwrap_or(self.selected.min(last));
+
+        // Indices changed, so everything keyed by them is rebuilt
+        self.exit_selection_mode();
+        if self.shuffle {
+            self.shuffle_play_order();
+        }
+        self.update_search_filter();
+        self.refresh_listened();
+        self.refresh_read_only();
+        self.refresh_codec_info();
+        if added {
+            self.scan_missing_durations();
+            self.generate_missing_thumbnails();
+        }
+        if self.two_pane {
+            self.refresh_dir_tree();
+        }
+        Ok(())
+    }
+
+    /// Re-list the current directory from scratch, keeping the selection on the same
+    /// entry if it still exists
+    fn relist_dir(&mut self) -> io::Result<()> {
+        let selected_name = self.files.get(self.selected).cloned();
+        self.enter_dir(self.current_dir.clone())?;
+        if let Some(index) = selected_name.and_then(|name| self.files.iter().position(|f| *f == name)) {
+            self.selected = index;
+        }
+        Ok(())
+    }
+
+    /// Toggle listing of dot-files and dot-folders (`Ctrl+H` or `Ctrl+.`)
+    pub fn toggle_hidden_files(&mut self) {
+        self.show_hidden_files = !self.show_hidden_files;
+        match self.refresh_files() {
+            Ok(()) if self.show_hidden_files => self.set_status("Showing hidden files".into()),
+            Ok(()) => self.set_status("Hiding hidden files".into()),
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Re-read which files of the current directory are read-only
+    fn refresh_read_only(&mut self) {
+        self.read_only = self.files.iter()
+            .filter(|f| **f != "..." && !f.ends_with('/'))
+            .filter(|f| {
+                fs::metadata(self.current_dir.join(f))
+                    .map(|meta| meta.permissions().readonly())
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+    }
+
+    /// Format the permissions of a file, e.g. `rw-r--r-- (644)` on Unix
+    /// or `Read-only: yes` on other platforms
+    pub fn file_perms(path: &Path) -> String {
+        let permissions = match fs::metadata(path) {
+            Ok(meta) => meta.permissions(),
+            Err(e) => return format!("unavailable ({})", e),
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = permissions.mode() & 0o777;
+            let rwx: String = (0..9)
+                .map(|i| {
+                    let bit = 0o400 >> i;
+                    match (mode & bit != 0, i % 3) {
+                        (false, _) => '-',
+                        (true, 0) => 'r',
+                        (true, 1) => 'w',
+                        (true, _) => 'x',
+                    }
+                })
+                .collect();
+            format!("{} ({:o})", rwx, mode)
+        }
+
+        #[cfg(not(unix))]
+        {
+            format!("Read-only: {}", if permissions.readonly() { "yes" } else { "no" })
+        }
+    }
+
+    /// Permissions line for the selected entry, shown while the file info panel is open
+    pub fn show_file_permissions(&self) -> Option<String> {
+        if !self.show_file_info {
+            return None;
+        }
+        let name = self.files.get(self.selected).filter(|f| **f != "...")?;
+        let path = self.current_dir.join(name.trim_end_matches('/'));
+        Some(format!("Permissions: {}", Self::file_perms(&path)))
+    }
+
+    /// Root of the music library: `music_dir` from the config, or the startup directory
+    fn music_dir(&self) -> PathBuf {
+        self.config.music_dir.as_ref()
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(|| self.initial_dir.clone())
+    }
+
+    /// Sum the size of all audio files under the music directory in a background thread.
+    /// The result arrives through `poll_library_size` and is cached for the session.
+    pub fn compute_library_size(&mut self) {
+        let root = self.music_dir();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.lib_size_rx = Some(rx);
+        self.total_library_size_cached = None;
+        self.total_library_tracks_cached = None;
+
+        std::thread::spawn(move || {
+            let (bytes, tracks) = walkdir::WalkDir::new(root)
+                .into_iter()
+                .filter_map(|entry| entry.ok()) // skips permission-denied directories
+                .filter(|entry| entry.file_type().is_file() && is_audio_file(entry.path()))
+                .filter_map(|entry| entry.metadata().ok())
+                .fold((0u64, 0usize), |(bytes, tracks), meta| (bytes + meta.len(), tracks + 1));
+            let _ = tx.send((bytes, tracks));
+        });
+    }
+
+    /// Pick up the library size once the background scan has finished
+    pub fn poll_library_size(&mut self) {
+        if let Some(Ok((bytes, tracks))) = self.lib_size_rx.as_ref().map(|rx| rx.try_recv()) {
+            self.total_library_size_cached = Some(bytes);
+            self.total_library_tracks_cached = Some(tracks);
+            self.lib_size_rx = None;
+            self.dirty = true;
+        }
+    }
+
+    /// Measure the audio output latency in a background thread;
+    /// the result arrives through `poll_audio_latency`
+    pub fn monitor_audio_latency(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.latency_rx = Some(rx);
+
+        let player = self.player.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(player.measure_latency());
+        });
+    }
+
+    /// Pick up the measured latency once the background measurement has finished
+    pub fn poll_audio_latency(&mut self) {
+        if let Some(Ok(latency)) = self.latency_rx.as_ref().map(|rx| rx.try_recv()) {
+            self.audio_latency = latency;
+            self.latency_rx = None;
+            self.dirty = true;
+        }
+    }
+
+    /// Seek the current track to `ms` milliseconds, compensating for the measured output latency
+    pub fn seek_to(&mut self, ms: u64) -> Result<(), PlayerError> {
+        let target = Duration::from_millis(ms).saturating_sub(self.audio_latency.unwrap_or_default());
+        self.player.seek_to(target)
+    }
+
+    /// Seek forward by SEEK_STEP_SECS (`→`/`l`), stopping at the end of the track
+    pub fn seek_forward(&mut self) {
+        let mut target = self.current_time + SEEK_STEP_SECS * 1000;
+        if self.total_time > 0 {
+            target = target.min(self.total_time);
+        }
+        self.seek_relative(target);
+    }
+
+    /// Seek backward by SEEK_STEP_SECS (`←`/`h`), stopping at the start of the track
+    pub fn seek_backward(&mut self) {
+        self.seek_relative(self.current_time.saturating_sub(SEEK_STEP_SECS * 1000));
+    }
+
+    fn seek_relative(&mut self, target: u64) {
+        if self.playing.is_none() {
+            return;
+        }
+        match self.seek_to(target) {
+            Ok(()) => {
+                // Show the new position right away instead of on the next progress update
+                self.current_time = target;
+                self.set_status(match target / 1000 {
+                    0 => "Seeked to start".into(),
+                    secs => format!("Seeked to {}", Self::format_duration(secs)),
+                });
+            }
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Mark the start of the A-B loop at the current position (`[`)
+    pub fn mark_loop_a(&mut self) {
+        if self.playing.is_none() {
+            return;
+        }
+        if self.loop_b.is_some_and(|b| self.current_time >= b) {
+            self.set_status("Loop start must be before the end (B)".into());
+            return;
+        }
+        self.loop_a = Some(self.current_time);
+        self.set_status(format!("Loop A: {}", Self::format_position(self.current_time)));
+    }
+
+    /// Mark the end of the A-B loop at the current position (`]`)
+    pub fn mark_loop_b(&mut self) {
+        if self.playing.is_none() {
+            return;
+        }
+        if self.loop_a.is_some_and(|a| self.current_time <= a) {
+            self.set_status("Loop end must be after the start (A)".into());
+            return;
+        }
+        self.loop_b = Some(self.current_time);
+        self.set_status(format!("Loop B: {}", Self::format_position(self.current_time)));
+    }
+
+    /// Clear both loop markers (`\`)
+    pub fn clear_loop(&mut self) {
+        if self.loop_a.take().is_some() | self.loop_b.take().is_some() {
+            self.set_status("Loop cleared".into());
+        }
+    }
+
+    /// The A-B loop, once both markers are set
+    pub fn ab_loop(&self) -> Option<(u64, u64)> {
+        self.loop_a.zip(self.loop_b)
+    }
+
+    /// Jump back to A when playback reaches B
+    pub fn check_ab_loop(&mut self) {
+        let Some((a, b)) = self.ab_loop() else {
+            return;
+        };
+        if self.playing.is_some() && self.current_time >= b {
+            match self.seek_to(a) {
+                Ok(()) => self.current_time = a,
+                Err(e) => self.push_notification(&format!("Error: {}", e)),
+            }
+        }
+    }
+
+    /// Seek to a fraction (0.0–1.0) of the current track, e.g. from a click on the progress bar
+    pub fn seek_to_fraction(&mut self, fraction: f64) {
+        if self.total_time == 0 {
+            return;
+        }
+        let target = (fraction.clamp(0.0, 1.0) * self.total_time as f64) as u64;
+        self.seek_relative(target);
+    }
+
+    /// Whole seconds left in the current track, rounded up so it only reaches zero
+    /// at the very end. None while the duration is unknown.
+    pub fn remaining_secs(&self) -> Option<u64> {
+        if self.total_time == 0 {
+            return None;
+        }
+        self.total_time.checked_sub(self.current_time).map(|ms| ms.div_ceil(1000))
+    }
+
+    /// Format a duration as `MM:SS`, or `H:MM:SS` from one hour on.
+    /// Zero means "unknown" and is shown as `--:--`.
+    pub fn format_duration(secs: u64) -> String {
+        if secs == 0 {
+            "--:--".into()
+        } else if secs >= 3600 {
+            format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+        } else {
+            format!("{:02}:{:02}", secs / 60, secs % 60)
+        }
+    }
+
+    /// Format a playback position in milliseconds as `MM:SS.t` (tenths of a second),
+    /// or `H:MM:SS.t` from one hour on
+    pub fn format_position(ms: u64) -> String {
+        let (secs, tenths) = (ms / 1000, ms % 1000 / 100);
+        if secs >= 3600 {
+            format!("{}:{:02}:{:02}.{}", secs / 3600, secs % 3600 / 60, secs % 60, tenths)
+        } else {
+            format!("{:02}:{:02}.{}", secs / 60, secs % 60, tenths)
+        }
+    }
+
+    /// Latency line for the stats overlay
+    pub fn latency_summary(&self) -> String {
+        match self.audio_latency {
+            Some(latency) => format!("Audio latency: {}ms", latency.as_millis()),
+            None => "Audio latency: unknown".into(),
+        }
+    }
+
+    /// Whole seconds played this session
+    pub fn total_session_time_secs(&self) -> u64 {
+        self.session_time_ms / 1000
+    }
+
+    /// Session summary for the top bar, e.g. `Played: 3 songs, 12m34s`
+    pub fn session_summary(&self) -> String {
+        let secs = self.total_session_time_secs();
+        let time = if secs >= 3600 {
+            format!("{}h{:02}m{:02}s", secs / 3600, secs % 3600 / 60, secs % 60)
+        } else {
+            format!("{}m{:02}s", secs / 60, secs % 60)
+        };
+        let songs = if self.songs_played == 1 { "song" } else { "songs" };
+        format!("Played: {} {}, {}", self.songs_played, songs, time)
+    }
+
+    /// Start counting songs and playback time from zero (`Ctrl+R`)
+    pub fn reset_session_stats(&mut self) {
+        self.songs_played = 0;
+        self.session_time_ms = 0;
+        self.set_status("Session stats reset".into());
+    }
+
+    /// Library summary for the stats overlay
+    pub fn library_summary(&self) -> String {
+        match (self.total_library_size_cached, self.total_library_tracks_cached) {
+            (Some(bytes), Some(tracks)) => format!(
+                "Library: {:.2} GB across {} tracks",
+                bytes as f64 / 1_000_000_000.0,
+                tracks
+            ),
+            _ => "Library: scanning...".into(),
+        }
+    }
+
+    /// Toggle the stats overlay (`:stats`)
+    pub fn toggle_stats(&mut self) {
+        self.show_stats = !self.show_stats;
+    }
+
+    /// Toggle the file info panel (`I`)
+    pub fn toggle_file_info(&mut self) {
+        self.show_file_info = !self.show_file_info;
+    }
+
+    /// Show or hide the track info panel (`i`)
+    pub fn toggle_track_info(&mut self) {
+        self.show_track_info = !self.show_track_info;
+    }
+
+    /// Have the player report signal levels to `levels`
+    fn meter_levels(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.player.set_level_sender(tx);
+        self.level_rx = Some(rx);
+    }
+
+    /// Move new signal levels into `levels`, keeping the last LEVEL_HISTORY
+    pub fn poll_levels(&mut self) {
+        if let Some(rx) = &self.level_rx {
+            let before = self.levels.len();
+            self.levels.extend(rx.try_iter());
+            self.dirty |= self.show_visualizer && self.levels.len() != before;
+        }
+        let excess = self.levels.len().saturating_sub(LEVEL_HISTORY);
+        self.levels.drain(..excess);
+    }
+
+    /// Show or hide the album art panel (`a`); it needs 24-bit color
+    pub fn toggle_art(&mut self) {
+        if !self.show_art && !art::truecolor_supported() {
+            self.set_status("Album art needs a true color terminal (COLORTERM=truecolor)".into());
+            return;
+        }
+        self.show_art = !self.show_art;
+    }
+
+    /// Show or hide the lyrics panel (`l`)
+    pub fn toggle_lyrics(&mut self) {
+        self.show_lyrics = !self.show_lyrics;
+    }
+
+    /// Index of the lyric line at the current position, if it has started
+    pub fn current_lyric(&self) -> Option<usize> {
+        lyrics::current_line(&self.lyrics, Duration::from_millis(self.current_time))
+    }
+
+    /// Cover of the playing track, read once per track
+    pub fn load_current_art(&mut self) -> Option<&DynamicImage> {
+        let path = self.playing.clone()?;
+        if self.current_art.as_ref().is_none_or(|(loaded, _)| *loaded != path) {
+            // The playing track's own folder, which may no longer be the one browsed
+            let dir_art = path.parent().and_then(Self::detect_album_art_file);
+            let image = art::extract_album_art(&path, dir_art.as_deref());
+            self.current_art = Some((path, image));
+        }
+        self.current_art.as_ref()?.1.as_ref()
+    }
+
+    /// Show or hide the level visualizer (`v`)
+    pub fn toggle_visualizer(&mut self) {
+        self.show_visualizer = !self.show_visualizer;
+    }
+
+    /// Tags and technical details of the playing track, read once per track
+    pub fn load_current_meta(&mut self) -> Option<&TrackMeta> {
+        let path = self.playing.clone()?;
+        if self.current_meta.is_none() {
+            let codec = codec::read_codec_info(&path);
+            let (sample_rate, channels) = player::stream_format(&path).unzip();
+            self.current_meta = Some(TrackMeta {
+                bitrate_kbps: codec.bitrate_kbps,
+                vbr: codec.vbr,
+                sample_rate,
+                channels,
+                file_size: fs::metadata(&path).ok().map(|meta| meta.len()),
+                ..tagread::read_tags(&path)
+            });
+        }
+        self.current_meta.as_ref()
+    }
+
+    /// Tags of a file, read once and then served from `meta_cache`
+    pub fn meta(&mut self, path: &Path) -> &TrackMeta {
+        self.meta_cache
+            .entry(path.to_path_buf())
+            .or_insert_with(|| tagread::read_tags(path))
+    }
+
+    /// Switch to another sort mode and re-sort the list
+    pub fn set_sort(&mut self, mode: SortBy) {
+        self.sort_mode = mode;
+        self.apply_sort();
+        self.refresh_listened();
+        self.set_status(format!("Sorted by {}", mode.label()));
+    }
+
+    /// Cycle through the sort modes (`o`)
+    pub fn cycle_sort(&mut self) {
+        self.set_sort(self.sort_mode.next());
+    }
+
+    /// Re-sort the file list by the current sort mode, keeping the selection in place
+    fn apply_sort(&mut self) {
+        let selected_name = self.files.get(self.selected).cloned();
+
+        match self.sort_mode {
+            SortBy::Name => self.files.sort_by(|a, b| compare_by_name(a, b)),
+            SortBy::NameDesc => self.files.sort_by(|a, b| list_group(a).cmp(&list_group(b)).then_with(|| compare_by_name(b, a))),
+            SortBy::Duration => self.files_by_duration(),
+            SortBy::Size => self.files_by_metadata(|meta| meta.len(), Ord::cmp),
+            SortBy::Modified => self.files_by_metadata(|meta| meta.modified().ok(), |a, b| b.cmp(a)),
+            SortBy::Bpm => self.files_by_bpm(),
+        }
+
+        if let Some(name) = selected_name {
+            self.selected = self.files.iter().position(|f| *f == name).unwrap_or(0);
+        }
+
+        // Indices changed, so the shuffle order and search results have to be rebuilt
+        if self.shuffle {
+            self.shuffle_play_order();
+        }
+        self.update_search_filter();
+    }
+
+    /// Toggle shuffled playback (`s`). The file list keeps its order; only the
+    /// track picked next changes. Turning it off continues in list order.
+    pub fn toggle_shuffle(&mut self) {
+        self.shuffle = !self.shuffle;
+        if self.shuffle {
+            self.shuffle_play_order();
+            self.set_status("Shuffle on".into());
+        } else {
+            self.play_order.clear();
+            self.set_status("Shuffle off".into());
+        }
+    }
+
+    /// Cycle the repeat mode: off, repeat one, repeat all (`r`)
+    pub fn cycle_repeat(&mut self) {
+        self.repeat = self.repeat.next();
+        self.set_status(match self.repeat {
+            RepeatMode::Off => "Repeat off".into(),
+            RepeatMode::One => "Repeat one".into(),
+            RepeatMode::All => "Repeat all".into(),
+        });
+    }
+
+    /// The active color theme; the first one includes the colors set in the config
+    /// and the environment
+    pub fn current_theme(&self) -> Theme {
+        match self.theme_index % THEMES.len() {
+            0 => Theme::from_config(&self.theme_overrides()),
+            index => THEMES[index](),
+        }
+    }
+
+    /// Colors replacing the default theme's: the config's, then the environment's over them
+    fn theme_overrides(&self) -> ThemeConfig {
+        self.config.theme.overridden_by(&self.theme_env)
+    }
+
+    /// Switch to the next built-in theme (`t`)
+    pub fn cycle_theme(&mut self) {
+        self.theme_index = (self.theme_index + 1) % THEMES.len();
+        self.set_status(format!("Theme: {}", self.current_theme().name));
+    }
+
+    /// Names of the tracks that play next: queued tracks first, then the list in
+    /// play order (shuffled or not) from the selection on, wrapping with repeat-all
+    pub fn upcoming_tracks(&self) -> Vec<String> {
+        let mut upcoming: Vec<String> = self.queue.iter()
+            .map(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default())
+            .collect();
+
+        let order: Vec<usize> = if self.shuffle && !self.play_order.is_empty() {
+            self.play_order.clone()
+        } else {
+            (0..self.files.len()).filter(|&i| is_playable_entry(&self.files[i])).collect()
+        };
+
+        // Split after the selected entry (or where it would be in list order)
+        let split = match order.iter().position(|&i| i == self.selected) {
+            Some(pos) => pos + 1,
+            None if self.shuffle => 0,
+            None => order.iter().take_while(|&&i| i < self.selected).count(),
+        };
+        let (before, after) = order.split_at(split);
+
+        let mut indices = after.to_vec();
+        if self.repeat == RepeatMode::All {
+            indices.extend_from_slice(before);
+        }
+        upcoming.extend(indices.into_iter().map(|i| self.files[i].clone()));
+
+        upcoming.truncate(UPCOMING_LIMIT);
+        upcoming
+    }
+
+    /// Build a new random permutation of the audio files in the list
+    fn shuffle_play_order(&mut self) {
+        self.play_order = self.files.iter()
+            .enumerate()
+            .filter(|(_, f)| is_playable_entry(f))
+            .map(|(i, _)| i)
+            .collect();
+        fastrand::shuffle(&mut self.play_order);
+    }
+
+    /// Sort audio files by their ID3 `TBPM` value, slowest first.
+    /// Folders stay on top and files without a BPM tag go last.
+    pub fn files_by_bpm(&mut self) {
+        let bpms: HashMap<String, f32> = self.files.clone().into_iter()
+            .filter(|f| is_track_entry(f))
+            .filter_map(|f| {
+                let bpm = self.meta(&self.current_dir.join(&f)).bpm?;
+                Some((f, bpm))
+            })
+            .collect();
+        self.sort_tracks_by(&bpms, f32::total_cmp);
+    }
+
+    /// Sort tracks by length. Small directories are measured first; in bigger ones
+    /// the tracks the background scan hasn't reached yet go last.
+    fn files_by_duration(&mut self) {
+        let tracks: Vec<String> = self.files.iter().filter(|f| is_track_entry(f)).cloned().collect();
+        if tracks.len() <= MAX_TRACKS_MEASURED_FOR_SORT {
+            for name in &tracks {
+                if !self.durations.contains_key(name) {
+                    if let Some(duration) = player::track_duration(&self.current_dir.join(name)) {
+                        self.durations.insert(name.clone(), duration.as_secs());
+                    }
+                }
+            }
+        }
+        let durations = self.durations.clone();
+        self.sort_tracks_by(&durations, Ord::cmp);
+    }
+
+    /// Sort tracks by a value of their file metadata, such as the size
+    fn files_by_metadata<K>(&mut self, key: impl Fn(&fs::Metadata) -> K, compare: impl Fn(&K, &K) -> Ordering) {
+        let keys: HashMap<String, K> = self.files.iter()
+            .filter(|f| is_track_entry(f))
+            .filter_map(|f| Some((f.clone(), key(&fs::metadata(self.current_dir.join(f)).ok()?))))
+            .collect();
+        self.sort_tracks_by(&keys, compare);
+    }
+
+    /// Sort the list with "..." and folders first in name order, then the tracks by
+    /// their key; tracks without one go last, and ties are in name order
+    fn sort_tracks_by<K>(&mut self, keys: &HashMap<String, K>, compare: impl Fn(&K, &K) -> Ordering) {
+        self.files.sort_by(|a, b| {
+            list_group(a).cmp(&list_group(b))
+                .then_with(|| match (keys.get(a), keys.get(b)) {
+                    (Some(x), Some(y)) => compare(x, y),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                })
+                .then_with(|| compare_by_name(a, b))
+        });
+    }
+
+    /// Re-read the `.listened` markers of the current directory and, if enabled,
+    /// move listened files below the others while keeping the selection in place
+    fn refresh_listened(&mut self) {
+        self.listened = self.files.iter()
+            .filter(|f| **f != "..." && !f.ends_with('/'))
+            .filter(|f| listened_marker(&self.current_dir.join(f)).exists())
+            .cloned()
+            .collect();
+
+        if self.config.sink_listened {
+            let selected_name = self.files.get(self.selected).cloned();
+            // Stable sort keeps "...", folders and the alphabetical order intact
+            let listened = &self.listened;
+            self.files.sort_by_key(|f| listened.contains(f));
+            if let Some(name) = selected_name {
+                self.selected = self.files.iter().position(|f| *f == name).unwrap_or(0);
+            }
+        }
+    }
+
+    /// Mark a file as heard by creating an empty `<file>.listened` marker next to it
+    pub fn mark_as_listened(&mut self, path: &Path) -> io::Result<()> {
+        fs::File::create(listened_marker(path))?;
+        self.refresh_listened();
+        Ok(())
+    }
+
+    /// Toggle the `.listened` marker of the selected file
+    pub fn toggle_listened(&mut self) {
+        let Some(name) = self.files.get(self.selected).cloned() else {
+            return;
+        };
+        if !is_track_entry(&name) {
+            return;
+        }
+
+        let path = self.current_dir.join(&name);
+        let result = if self.listened.contains(&name) {
+            fs::remove_file(listened_marker(&path)).map(|_| {
+                self.refresh_listened();
+                format!("Marked as unheard: {}", name)
+            })
+        } else {
+            self.mark_as_listened(&path).map(|_| format!("Marked as listened: {}", name))
+        };
+
+        match result {
+            Ok(message) => self.set_status(message),
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Start renaming the highlighted file (`F2`), editing its name without the extension
+    pub fn start_rename(&mut self) {
+        let Some(name) = self.files.get(self.selected) else {
+            return;
+        };
+        if name == "..." || name.ends_with('/') {
+            self.set_status("Folders can't be renamed".into());
+            return;
+        }
+        if name.starts_with(STREAM_PREFIX) {
+            self.set_status("Streams can't be renamed".into());
+            return;
+        }
+        let stem = Path::new(name).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        self.rename_input = Some(stem);
+        self.set_status("Rename: press Enter to confirm, Esc to cancel".into());
+    }
+
+    /// Leave the rename field without touching the file (`Esc`)
+    pub fn cancel_rename(&mut self) {
+        self.rename_input = None;
+        self.set_status("Rename cancelled".into());
+    }
+
+    /// Rename the highlighted file to the typed name, keeping its extension (`Enter`).
+    /// The `.listened` marker moves along and the selection stays on the file.
+    pub fn confirm_rename(&mut self) {
+        let Some(new_stem) = self.rename_input.take() else {
+            return;
+        };
+        let Some(name) = self.files.get(self.selected).cloned() else {
+            return;
+        };
+        let new_stem = new_stem.trim();
+        if new_stem.is_empty() || new_stem.contains(['/', '\\']) {
+            self.set_status(format!("Invalid file name: {:?}", new_stem));
+            return;
+        }
+
+        let old_path = self.current_dir.join(&name);
+        let new_file_name = match Path::new(&name).extension() {
+            Some(ext) => format!("{}.{}", new_stem, ext.to_string_lossy()),
+            None => new_stem.to_string(),
+        };
+        let new_path = old_path.with_file_name(&new_file_name);
+        if new_path == old_path {
+            self.set_status("Name unchanged".into());
+            return;
+        }
+        if new_path.exists() {
+            self.push_notification(&format!("Error: {} already exists", new_file_name));
+            return;
+        }
+
+        if let Err(e) = fs::rename(&old_path, &new_path) {
+            self.push_notification(&format!("Error: {}", e));
+            return;
+        }
+        let marker = listened_marker(&old_path);
+        if marker.exists() {
+            let _ = fs::rename(&marker, listened_marker(&new_path));
+        }
+        if self.playing.as_deref() == Some(old_path.as_path()) {
+            self.playing = Some(new_path.clone());
+        }
+
+        // Entries of a recursive listing are relative paths, so swap only the file name
+        let new_entry = Path::new(&name).with_file_name(&new_file_name).to_string_lossy().into_owned();
+        match self.refresh_files() {
+            Ok(()) => {
+                if let Some(index) = self.files.iter().position(|f| *f == new_entry) {
+                    self.selected = index;
+                }
+                self.set_status(format!("Renamed to {}", new_file_name));
+            }
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Restore the volume saved for the current directory, if enabled in the config
+    pub fn remember_volume_per_directory(&mut self) {
+        if !self.config.remember_volume_per_dir {
+            return;
+        }
+        if let Some(&volume) = self.dir_volumes.get(&self.current_dir) {
+            self.apply_volume(volume);
+        }
+    }
+
+    /// Set the playback volume (clamped to 0–200%) and remember it for this directory
+    pub fn set_volume(&mut self, volume: f32) {
+        let volume = self.apply_volume(volume);
+        if self.config.remember_volume_per_dir {
+            self.dir_volumes.insert(self.current_dir.clone(), volume);
+        }
+    }
+
+    /// Raise the volume by one step (`+`)
+    pub fn volume_up(&mut self) {
+        self.set_volume(self.volume + VOLUME_STEP);
+    }
+
+    /// Lower the volume by one step (`-`)
+    pub fn volume_down(&mut self) {
+        self.set_volume(self.volume - VOLUME_STEP);
+    }
+
+    /// Change the playback speed (`<`/`>`); the playing track restarts at the same position
+    pub fn set_speed(&mut self, speed: f32, progress_tx: &Sender<PlayerEvent>) {
+        // Round to a tenth so repeated steps don't drift
+        let speed = (speed.clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end()) * 10.0).round() / 10.0;
+        if speed == self.speed {
+            return;
+        }
+        self.speed = speed;
+        self.player.set_speed(speed);
+        self.seek_to_current_offset(progress_tx);
+        self.set_status(format!("Speed: {:.1}×", speed));
+    }
+
+    pub fn speed_up(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        self.set_speed(self.speed + SPEED_STEP, progress_tx);
+    }
+
+    pub fn speed_down(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        self.set_speed(self.speed - SPEED_STEP, progress_tx);
+    }
+
+    /// Restart the current track and seek back to where it was, so settings that are
+    /// applied when a track starts (equalizer, speed) take effect right away
+    fn seek_to_current_offset(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        if let Some(path) = &self.playing {
+            let _ = self.player.play_file(path, progress_tx.clone());
+            self.requeue_preloaded();
+            self.pending_resume = Some(self.current_time);
+        }
+    }
+
+    fn apply_volume(&mut self, volume: f32) -> f32 {
+        self.volume = volume.clamp(0.0, 2.0);
+        self.player.set_volume(self.volume);
+        self.volume
+    }
+
+    /// Persist session state (per-directory volumes, recent directories, the open
+    /// directory and its selection) for the next start
+    pub fn save_state(&self) -> Result<(), String> {
+        State {
+            dir_volumes: self.dir_volumes.clone(),
+            recent_dirs: self.recent_dirs.clone(),
+            last_dir: Some(self.current_dir.clone()),
+            last_selected: self.selected,
+            last_played: self.last_played.clone(),
+        }
+        .save()
+    }
+
+    /// Save the queue for the next start. A track preloaded for gapless playback
+    /// hasn't started yet, so it goes first.
+    pub fn save_queue(&self) -> io::Result<()> {
+        queue::save(self.preloaded.iter().chain(&self.queue))
+    }
+
+    /// Put the tracks saved by `save_queue` back in the queue (skipped with
+    /// `--no-restore-queue`)
+    pub fn restore_queue(&mut self) {
+        let restored = queue::load();
+        if !restored.is_empty() {
+            self.set_status(format!("Restored {}-track queue", restored.len()));
+            self.queue.extend(restored);
+        }
+    }
+
+    pub fn next(&mut self) {
+        if self.search_query.is_some() {
+            self.step_filtered(1);
+        } else if !self.files.is_empty() {
+            self.selected = (self.selected + 1) % self.files.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if self.search_query.is_some() {
+            self.step_filtered(self.filtered_indices.len().saturating_sub(1));
+        } else if !self.files.is_empty() {
+            if self.selected == 0 {
+                self.selected = self.files.len() - 1;
+            } else {
+                self.selected -= 1;
+            }
+        }
+    }
+
+    /// Move the selection one page down (`PageDown`), stopping at the last entry
+    pub fn page_down(&mut self) {
+        let step = self.list_height.saturating_sub(1).max(1) as isize;
+        self.move_visible(step);
+    }
+
+    /// Move the selection one page up (`PageUp`), stopping at the first entry
+    pub fn page_up(&mut self) {
+        let step = self.list_height.saturating_sub(1).max(1) as isize;
+        self.move_visible(-step);
+    }
+
+    /// Select the first listed entry (`Home`)
+    pub fn select_first(&mut self) {
+        self.move_visible(isize::MIN);
+    }
+
+    /// Select the last listed entry (`End`)
+    pub fn select_last(&mut self) {
+        self.move_visible(isize::MAX);
+    }
+
+    /// Move the selection by `delta` rows of the visible list, clamped to its ends
+    fn move_visible(&mut self, delta: isize) {
+        let visible = self.visible_indices();
+        if visible.is_empty() {
+            return;
+        }
+        let pos = visible.iter().position(|&i| i == self.selected).unwrap_or(0) as isize;
+        let target = pos.saturating_add(delta).clamp(0, visible.len() as isize - 1);
+        self.selected = visible[target as usize];
+    }
+
+    /// Move the selection `step` places forward through the search results, wrapping around
+    fn step_filtered(&mut self, step: usize) {
+        if self.filtered_indices.is_empty() {
+            return;
+        }
+        let pos = self.filtered_indices.iter().position(|&i| i == self.selected).unwrap_or(0);
+        self.selected = self.filtered_indices[(pos + step) % self.filtered_indices.len()];
+    }
+
+    /// Indices into `files` of the entries shown in the list: the search results
+    /// while searching, everything otherwise
+    pub fn visible_indices(&self) -> Vec<usize> {
+        match self.search_query {
+            Some(_) => self.filtered_indices.clone(),
+            None => (0..self.files.len()).collect(),
+        }
+    }
+
+    /// Index into `files` of the playing track, if it is listed
+    pub fn playing_index(&self) -> Option<usize> {
+        let playing = self.playing.as_ref()?;
+        self.files.iter().position(|f| self.current_dir.join(f) == *playing)
+    }
+
+    /// True for the tracks listed after the playing one: the part of the list
+    /// that plays next and can be reordered with `Shift+↑`/`Shift+↓`
+    pub fn is_queued_entry(&self, index: usize) -> bool {
+        self.files.get(index).is_some_and(|f| is_track_entry(f))
+            && self.playing_index().is_some_and(|playing| index > playing)
+    }
+
+    /// Exchange two entries of the list, keeping the selection and the shuffle order
+    /// on the same tracks. Lasts until the list is sorted again.
+    pub fn swap_entries(&mut self, a: usize, b: usize) {
+        if a >= self.files.len() || b >= self.files.len() {
+            return;
+        }
+        let swapped = |i: usize| if i == a { b } else if i == b { a } else { i };
+        self.files.swap(a, b);
+        self.selected = swapped(self.selected);
+        self.play_order.iter_mut().for_each(|i| *i = swapped(*i));
+    }
+
+    /// Move the selected upcoming track one place earlier (`Shift+↑`)
+    pub fn move_up(&mut self) {
+        self.move_queued(false);
+    }
+
+    /// Move the selected upcoming track one place later (`Shift+↓`)
+    pub fn move_down(&mut self) {
+        self.move_queued(true);
+    }
+
+    fn move_queued(&mut self, down: bool) {
+        if !self.is_queued_entry(self.selected) {
+            self.set_status("Only the tracks after the playing one can be moved".into());
+            return;
+        }
+        let target = if down { self.selected + 1 } else { self.selected.wrapping_sub(1) };
+        if self.is_queued_entry(target) {
+            self.swap_entries(self.selected, target);
+        }
+    }
+
+    /// Select the first entry starting with `c` (case-insensitive); pressing the same
+    /// letter again cycles through all entries starting with it
+    pub fn jump_to_letter(&mut self, c: char) {
+        if self.files.is_empty() || self.search_query.is_some() {
+            return;
+        }
+
+        let c = c.to_lowercase().next().unwrap_or(c);
+        let matches: Vec<usize> = self.files.iter()
+            .enumerate()
+            .filter(|(_, f)| f.chars().next().and_then(|first| first.to_lowercase().next()) == Some(c))
+            .map(|(i, _)| i)
+            .collect();
+
+        if matches.is_empty() {
+            self.set_status(format!("Not found: {}", c));
+            return;
+        }
+
+        let pos = if self.last_jump_char == Some(c) {
+            (self.last_jump_pos + 1) % matches.len()
+        } else {
+            0
+        };
+        self.selected = matches[pos];
+        self.last_jump_char = Some(c);
+        self.last_jump_pos = pos;
+    }
+
+    /// Enter visual selection mode (`V`), starting with the highlighted track
+    pub fn enter_selection_mode(&mut self) {
+        self.selection_mode = true;
+        self.selected_files.clear();
+        self.confirm_delete = false;
+        self.toggle_file_selection();
+        self.set_status("Space - Toggle | Enter - Queue | Delete - Delete | Esc - Cancel".into());
+    }
+
+    /// Leave selection mode and forget the selection (`Esc`)
+    pub fn exit_selection_mode(&mut self) {
+        self.selection_mode = false;
+        self.selected_files.clear();
+        self.confirm_delete = false;
+    }
+
+    /// Add the highlighted track to the selection, or drop it if already selected (`Space`)
+    pub fn toggle_file_selection(&mut self) {
+        let Some(name) = self.files.get(self.selected).filter(|f| is_track_entry(f)).cloned() else {
+            return;
+        };
+        if !self.selected_files.remove(&name) {
+            self.selected_files.insert(name);
+        }
+    }
+
+    /// Move down (or up) in selection mode, extending the selection to the new entry
+    pub fn extend_selection(&mut self, down: bool) {
+        if down {
+            self.next();
+        } else {
+            self.previous();
+        }
+        if let Some(name) = self.files.get(self.selected).filter(|f| is_track_entry(f)) {
+            self.selected_files.insert(name.clone());
+        }
+    }
+
+    /// Selected tracks, in list order
+    fn selected_paths(&self) -> Vec<PathBuf> {
+        self.files.iter()
+            .filter(|f| self.selected_files.contains(*f))
+            .map(|f| self.current_dir.join(f))
+            .collect()
+    }
+
+    /// Add the selected tracks to the queue (`Enter`) and leave selection mode.
+    /// Playback starts with the first of them if nothing is playing.
+    pub fn enqueue_selection(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        let paths = self.selected_paths();
+        let count = paths.len();
+        self.queue.extend(paths);
+        self.exit_selection_mode();
+        if count > 0 && self.playing.is_none() {
+            self.play_next(progress_tx);
+        }
+        self.set_status(format!("Queued {} track{}", count, if count == 1 { "" } else { "s" }));
+    }
+
+    /// Delete the selected files from disk (`Delete`). Needs a second press in a row
+    /// to confirm.
+    pub fn delete_selection_with_confirm(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        let paths = self.selected_paths();
+        if paths.is_empty() {
+            self.set_status("Nothing selected".into());
+            return;
+        }
+        if !self.confirm_delete {
+            self.confirm_delete = true;
+            self.set_status(format!("Delete {} file{}? Press Delete again to confirm", paths.len(), if paths.len() == 1 { "" } else { "s" }));
+            return;
+        }
+
+        let mut deleted = 0;
+        let (mut playing_deleted, mut preload_deleted) = (false, false);
+        for path in &paths {
+            match fs::remove_file(path) {
+                Ok(()) => {
+                    deleted += 1;
+                    playing_deleted |= self.playing.as_ref() == Some(path);
+                    preload_deleted |= self.preloaded.as_ref() == Some(path);
+                    self.forget_deleted_file(path);
+                }
+                Err(e) => self.push_notification(&format!("Error: {} ({})", e, path.display())),
+            }
+        }
+        self.exit_selection_mode();
+        self.after_delete(playing_deleted, preload_deleted, progress_tx);
+        self.set_status(format!("Deleted {} file{}", deleted, if deleted == 1 { "" } else { "s" }));
+    }
+
+    /// Ask before deleting the highlighted file (`Delete`). The playing track is
+    /// paused while the question is open.
+    pub fn request_delete(&mut self) {
+        let Some(name) = self.files.get(self.selected).filter(|f| is_track_entry(f)) else {
+            self.set_status("Only files can be deleted".into());
+            return;
+        };
+        let path = self.current_dir.join(name);
+        self.set_status(format!("Delete '{}'? y/N", name));
+
+        self.paused_for_delete = self.playing.as_ref() == Some(&path) && !self.player.is_paused();
+        if self.paused_for_delete {
+            self.player.toggle_pause();
+        }
+        self.pending_delete = Some(path);
+    }
+
+    /// Keep the file asked about in `request_delete` and resume playback
+    pub fn cancel_delete(&mut self) {
+        self.pending_delete = None;
+        self.resume_after_delete();
+        self.set_status("Delete cancelled".into());
+    }
+
+    /// Delete the file asked about in `request_delete` (`y`). Deleting the playing
+    /// track moves on to the entry that takes its place in the list.
+    pub fn confirm_delete_file(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        let Some(path) = self.pending_delete.take() else {
+            return;
+        };
+        if let Err(e) = fs::remove_file(&path) {
+            self.resume_after_delete();
+            self.push_notification(&format!("Error: {} ({})", e, path.display()));
+            return;
+        }
+        let playing_deleted = self.playing.as_ref() == Some(&path);
+        let preload_deleted = self.preloaded.as_ref() == Some(&path);
+        self.forget_deleted_file(&path);
+        self.after_delete(playing_deleted, preload_deleted, progress_tx);
+
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        self.set_status(format!("Deleted {}", name));
+    }
+
+    /// Take a file deleted from disk out of the list, the queue and the gapless
+    /// preload, and remove its `.listened` marker
+    fn forget_deleted_file(&mut self, path: &Path) {
+        let _ = fs::remove_file(listened_marker(path));
+        self.queue.retain(|queued| queued != path);
+        if self.preloaded.as_deref() == Some(path) {
+            self.preloaded = None;
+        }
+        if let Some(index) = self.files.iter().position(|f| self.current_dir.join(f) == path) {
+            self.files.remove(index);
+            self.selected = self.selected.min(self.files.len().saturating_sub(1));
+        }
+    }
+
+    /// Catch up with files removed by `forget_deleted_file`. A deleted playing track
+    /// gives way to the entry that took its place in the list; a deleted preloaded
+    /// one is still in the player, so the current track restarts without it.
+    fn after_delete(&mut self, playing_deleted: bool, preload_deleted: bool, progress_tx: &Sender<PlayerEvent>) {
+        if self.shuffle {
+            self.shuffle_play_order();
+        }
+        self.update_search_filter();
+
+        if playing_deleted {
+            self.paused_for_delete = false;
+            let next = self.files.get(self.selected)
+                .filter(|f| is_track_entry(f))
+                .map(|f| self.current_dir.join(f));
+            match next {
+                Some(next) => self.play_path(next, progress_tx, None),
+                None => {
+                    self.stop();
+                }
+            }
+        } else if preload_deleted {
+            self.seek_to_current_offset(progress_tx);
+        }
+    }
+
+    /// Undo the pause of `request_delete`, if it paused anything
+    fn resume_after_delete(&mut self) {
+        if std::mem::take(&mut self.paused_for_delete) {
+            self.player.toggle_pause();
+        }
+    }
+
+    /// Start filtering the file list (`/`)
+    pub fn open_search(&mut self) {
+        self.search_query = Some(String::new());
+        self.update_search_filter();
+    }
+
+    /// Leave search mode and show the full list again; the selection stays on its entry
+    pub fn close_search(&mut self) {
+        self.search_query = None;
+        self.filtered_indices.clear();
+        self.search_mode = SearchMode::Fuzzy;
+        self.compiled_regex = None;
+        self.search_error = None;
+    }
+
+    /// Add a character to the search filter
+    pub fn search_push(&mut self, c: char) {
+        if let Some(query) = self.search_query.as_mut() {
+            query.push(c);
+            self.update_search_filter();
+        }
+    }
+
+    /// Remove the last character of the search filter; an empty filter ends the search
+    pub fn search_pop(&mut self) {
+        let Some(query) = self.search_query.as_mut() else {
+            return;
+        };
+        if query.pop().is_none() || query.is_empty() {
+            self.close_search();
+        } else {
+            self.update_search_filter();
+        }
+    }
+
+    /// Recompute `filtered_indices`, moving the selection to the first match if
+    /// the selected entry no longer matches. An invalid regex keeps the last results.
+    fn update_search_filter(&mut self) {
+        let Some(query) = &self.search_query else {
+            return;
+        };
+        match query.strip_prefix('~') {
+            Some(pattern) => {
+                self.search_mode = SearchMode::Regex;
+                match RegexBuilder::new(pattern).case_insensitive(true).build() {
+                    Ok(regex) => {
+                        self.compiled_regex = Some(regex);
+                        self.search_error = None;
+                    }
+                    Err(e) => {
+                        // The last line of a syntax error says what is wrong
+                        let message = e.to_string();
+                        self.search_error = Some(message.lines().last().unwrap_or_default().to_string());
+                        self.compiled_regex = None;
+                        return;
+                    }
+                }
+            }
+            None => {
+                self.search_mode = SearchMode::Fuzzy;
+                self.compiled_regex = None;
+                self.search_error = None;
+            }
+        }
+
+        self.filtered_indices = self.files.iter()
+            .enumerate()
+            .filter(|(_, f)| self.search_match(f).is_some())
+            .map(|(i, _)| i)
+            .collect();
+
+        if !self.filtered_indices.contains(&self.selected) {
+            if let Some(&first) = self.filtered_indices.first() {
+                self.selected = first;
+            }
+        }
+    }
+
+    /// Where the search query matches in `name`, if it does
+    pub fn search_match(&self, name: &str) -> Option<Range<usize>> {
+        let query = self.search_query.as_deref()?;
+        match self.search_mode {
+            SearchMode::Fuzzy => find_ignore_case(name, query),
+            SearchMode::Regex => self.compiled_regex.as_ref()?.find(name).map(|m| m.range()),
+        }
+    }
+
+    /// Advances selected to next MP3 file only, skipping folders, "..." and formats
+    /// that can't be played.
+    /// Returns true if advanced to a different mp3, false if no next mp3 found or only one mp3 exists.
+    /// Past the last track it only wraps around with `RepeatMode::All`.
+    pub fn next_mp3(&mut self) -> bool {
+        if self.files.is_empty() {
+            return false;
+        }
+
+        // Count how many mp3 files exist (exclude "..." and folders)
+        let mp3_count = self.files.iter()
+            .filter(|f| is_playable_entry(f))
+            .count();
+
+        // If only one mp3 file exists, don't advance
+        if mp3_count <= 1 {
+            return false;
+        }
+
+        // Shuffle: the track after the selected one in the play order
+        if self.shuffle && !self.play_order.is_empty() {
+            let next_pos = self.play_order.iter()
+                .position(|&i| i == self.selected)
+                .map_or(0, |pos| pos + 1);
+            if next_pos == self.play_order.len() && self.repeat != RepeatMode::All {
+                return false;
+            }
+            self.selected = self.play_order[next_pos % self.play_order.len()];
+            return true;
+        }
+
+        let start_index = self.selected;
+        let mut next_index = (start_index + 1) % self.files.len();
+
+        while !is_playable_entry(&self.files[next_index]) && next_index != start_index {
+            next_index = (next_index + 1) % self.files.len();
+        }
+
+        if next_index == start_index {
+            // No next mp3 found (shouldn't happen if mp3_count > 1, but safe check)
+            false
+        } else if next_index < start_index && self.repeat != RepeatMode::All {
+            // Reached the end of the list
+            false
+        } else {
+            self.selected = next_index;
+            true
+        }
+    }
+
+    /// Moves selected to the previous MP3 file, the mirror image of `next_mp3`.
+    /// Before the first track it only wraps around with `RepeatMode::All`.
+    pub fn previous_mp3(&mut self) -> bool {
+        let tracks: Vec<usize> = if self.shuffle && !self.play_order.is_empty() {
+            self.play_order.clone()
+        } else {
+            (0..self.files.len()).filter(|&i| is_playable_entry(&self.files[i])).collect()
+        };
+        if tracks.len() <= 1 {
+            return false;
+        }
+
+        let previous = match tracks.iter().position(|&i| i == self.selected) {
+            Some(0) if self.repeat == RepeatMode::All => tracks.last().copied(),
+            Some(pos) => pos.checked_sub(1).map(|pos| tracks[pos]),
+            // Selection on a folder: the last track above it
+            None => tracks.iter().rev().find(|&&i| i < self.selected).copied(),
+        };
+
+        match previous {
+            Some(index) => {
+                self.selected = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Play the previous track (`,`)
+    pub fn play_previous(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        if self.skip_cue_track(false) {
+            return;
+        }
+        if self.previous_mp3() {
+            let path = self.current_dir.join(&self.files[self.selected]);
+            self.play_path(path, progress_tx, None);
+        } else {
+            self.set_status("Start of list".into());
+        }
+    }
+
+    /// Open folder, go up, or play file based on selection
+    pub fn open_selected(&mut self, progress_tx: &Sender<PlayerEvent>) -> io::Result<()> {
+        if self.files.is_empty() {
+            self.set_status("No files or folders found".into());
+            return Ok(());
+        }
+
+        let selection = &self.files[self.selected];
+
+        if selection == "..." {
+            // Go up one directory if possible
+            if let Some(parent) = self.current_dir.parent() {
+                self.change_dir(parent.to_path_buf())?;
+
+                self.set_status(format!("Moved up to {:?}", self.current_dir));
+
+            } else {
+                self.set_status("Already at root directory".into());
+            }
+        } else if selection.ends_with('/') {
+            // Enter folder
+            let folder_name = selection.trim_end_matches('/');
+            let new_path = self.current_dir.join(folder_name);
+            if new_path.is_dir() {
+                self.change_dir(new_path)?;
+
+                self.set_status(format!("Entered folder {:?}", self.current_dir));
+
+            } else {
+                self.set_status(format!("Folder not found: {}", folder_name));
+            }
+        } else if let Some(url) = selection.strip_prefix(STREAM_PREFIX) {
+            self.play_url(url.to_string(), progress_tx);
+        } else if is_cue_file(Path::new(selection)) {
+            let sheet = self.current_dir.join(selection);
+            self.play_cue(&sheet, progress_tx);
+        } else if is_playlist_file(Path::new(selection)) {
+            let playlist = self.current_dir.join(selection);
+            self.play_playlist(&playlist, progress_tx)?;
+        } else {
+            // Play file
+            let file_path = self.current_dir.join(selection);
+            self.play_path(file_path, progress_tx, None);
+        }
+
+        Ok(())
+    }
+
+
+    /// Start playing a file and make it the current track, optionally crossfading into it
+    fn play_path(&mut self, path: PathBuf, progress_tx: &Sender<PlayerEvent>, fade: Option<Duration>) {
+        let result = match fade {
+            Some(fade) => self.player.crossfade_to(&path, fade.as_secs() as u32, progress_tx.clone()),
+            None => self.player.play_file(&path, progress_tx.clone()),
+        };
+        if let Err(e) = result {
+            self.report_play_error(e, &path);
+            return;
+        }
+        // Picking the preloaded track itself plays it now instead of again afterwards
+        if self.preloaded.as_ref() == Some(&path) {
+            self.preloaded = None;
+        }
+        self.requeue_preloaded();
+        self.begin_track(path);
+    }
+
+    /// The track preloaded for gapless playback went away with the player's current
+    /// track; put it back at the front of the queue so it still plays next
+    fn requeue_preloaded(&mut self) {
+        if let Some(next) = self.preloaded.take() {
+            self.queue.push_front(next);
+        }
+    }
+
+    fn report_play_error(&mut self, e: PlayerError, path: &Path) {
+        let message = match e {
+            PlayerError::Unsupported(message) => message.into(),
+            PlayerError::NoOutputDevice(_) => format!("Error: {} (press d to pick one)", e),
+            PlayerError::FileOpen(_) | PlayerError::Decode(_) => format!("Error: {} ({})", e, path.display()),
+            e => format!("Error: {}", e),
+        };
+        self.push_notification(&message);
+    }
+
+    /// Play the audio file of a cue sheet from its first track. Its tracks are
+    /// sections of the one file: the list and status bar show the track playing,
+    /// and `,`/`.` move between them.
+    pub fn play_cue(&mut self, sheet: &Path, progress_tx: &Sender<PlayerEvent>) {
+        let tracks = cue::parse_cue(sheet);
+        let Some(first) = tracks.first() else {
+            self.set_status(format!("Cue sheet has no tracks: {}", dir_basename(sheet)));
+            return;
+        };
+        let file = first.file.clone();
+        if !file.is_file() {
+            self.set_status(format!("Cue sheet file not found: {}", file.display()));
+            return;
+        }
+
+        if let Err(e) = self.player.play_file_from(&file, first.start_offset, progress_tx.clone()) {
+            self.report_play_error(e, &file);
+            return;
+        }
+        self.requeue_preloaded();
+        self.begin_track(file);
+        self.cue_tracks = tracks;
+        self.update_cue_track();
+    }
+
+    /// The cue sheet track playing now, if the playing file was opened from one
+    pub fn current_cue_track(&self) -> Option<&CueTrack> {
+        self.cue_index.and_then(|i| self.cue_tracks.get(i))
+    }
+
+    /// Follow playback through the tracks of the cue sheet, naming each as it starts
+    fn update_cue_track(&mut self) {
+        let Some(playing) = &self.playing else {
+            return;
+        };
+        let index = cue::current_track(&self.cue_tracks, playing, Duration::from_millis(self.current_time));
+        if index.is_none() || index == self.cue_index {
+            return;
+        }
+        self.cue_index = index;
+        if let Some(track) = self.current_cue_track() {
+            let label = match &track.performer {
+                Some(performer) => format!("{} — {}", performer, track.title),
+                None => track.title.clone(),
+            };
+            self.set_status(format!("  Playing: {}", label));
+        }
+    }
+
+    /// Seek to the start of the next (or previous) cue sheet track. False if there
+    /// is no such track in the playing file.
+    fn skip_cue_track(&mut self, forward: bool) -> bool {
+        let (Some(index), Some(playing)) = (self.cue_index, self.playing.clone()) else {
+            return false;
+        };
+        let target = if forward {
+            index + 1
+        } else {
+            match index.checked_sub(1) {
+                Some(previous) => previous,
+                None => return false,
+            }
+        };
+        let Some(track) = self.cue_tracks.get(target).filter(|track| track.file == playing) else {
+            return false;
+        };
+
+        let start = track.start_offset.as_millis() as u64;
+        match self.seek_to(start) {
+            Ok(()) => {
+                self.current_time = start;
+                self.update_cue_track();
+            }
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+        true
+    }
+
+    /// True while a stream URL plays; its length is unknown
+    pub fn is_streaming(&self) -> bool {
+        self.playing.as_ref().is_some_and(|path| stream::is_stream_url(&path.to_string_lossy()))
+    }
+
+    /// Open the stream URL prompt (`u`)
+    pub fn open_url_input(&mut self) {
+        self.url_input = Some(String::new());
+    }
+
+    /// Play the typed URL (`Enter`)
+    pub fn confirm_url(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        let Some(url) = self.url_input.take() else {
+            return;
+        };
+        let url = url.trim();
+        if url.is_empty() {
+            return;
+        }
+        if !stream::is_stream_url(url) {
+            self.set_status(format!("Not a stream URL: {}", url));
+            return;
+        }
+        self.play_url(url.to_string(), progress_tx);
+    }
+
+    /// Start playing a stream and list it as a `🌐` entry, so it can be played again
+    /// until the directory changes
+    fn play_url(&mut self, url: String, progress_tx: &Sender<PlayerEvent>) {
+        if let Err(e) = self.player.play_url(&url, progress_tx.clone()) {
+            self.push_notification(&format!("Error: {}", e));
+            return;
+        }
+
+        let entry = format!("{}{}", STREAM_PREFIX, url);
+        self.selected = match self.files.iter().position(|f| *f == entry) {
+            Some(index) => index,
+            None => {
+                self.files.push(entry);
+                self.update_search_filter();
+                self.files.len() - 1
+            }
+        };
+        self.requeue_preloaded();
+        self.begin_track(PathBuf::from(url));
+    }
+
+    /// Make `path` the current track once the player has started it
+    fn begin_track(&mut self, path: PathBuf) {
+        // Follow the track in the file list when it is listed there; in recursive
+        // mode entries are paths relative to the current directory
+        if let Ok(name) = path.strip_prefix(&self.current_dir) {
+            let name = name.to_string_lossy();
+            if let Some(index) = self.files.iter().position(|f| *f == name) {
+                self.selected = index;
+            }
+        }
+
+        let label = self.track_label(&path);
+        self.set_status(format!("  Playing: {}", label));
+        self.pending_intro_skip = (self.config.podcast_skip_secs > 0 && self.is_podcast(&path))
+            .then_some(self.config.podcast_skip_secs);
+        // Streams can't be resumed or replayed as files, so only tracks are remembered
+        let is_stream = stream::is_stream_url(&path.to_string_lossy());
+        if !is_stream {
+            self.record_history(&path);
+            self.last_played = Some(path.clone());
+        }
+        self.lyrics = lyrics::lrc_path(&path)
+            .map(|lrc| lyrics::parse_lrc(&lrc))
+            .unwrap_or_default();
+        self.cue_tracks.clear();
+        self.cue_index = None;
+        self.current_bpm = None;
+        self.playing = Some(path);
+        self.track_end_fired = false;
+        self.loop_a = None;
+        self.loop_b = None;
+        self.current_meta = None;
+        self.songs_played += 1;
+        self.update_now_playing();
+    }
+
+    /// Runs once per track when it plays to the end; the player may report the end
+    /// several times while the last samples drain, so repeats are ignored.
+    fn on_track_end_hook(&mut self, finished: &Path) {
+        if self.track_end_fired {
+            return;
+        }
+        self.track_end_fired = true;
+
+        if self.config.auto_mark_listened && !stream::is_stream_url(&finished.to_string_lossy()) {
+            if let Err(e) = fs::File::create(listened_marker(finished)) {
+                self.push_notification(&format!("Error: {}", e));
+            }
+            self.refresh_listened();
+        }
+    }
+
+    /// Remember a started track, appending it to the history log when enabled
+    fn record_history(&mut self, path: &Path) {
+        let entry = (path.to_path_buf(), history::now());
+        if self.config.save_history {
+            if let Err(e) = history::append(&entry) {
+                self.push_notification(&format!("Error: {}", e));
+            }
+        }
+
+        self.history.push(entry);
+        if self.history.len() > history::MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Toggle the history overlay (`H`)
+    pub fn toggle_history(&mut self) {
+        self.show_history = !self.show_history;
+    }
+
+    /// Report a non-fatal error: it replaces the status bar text for a few seconds
+    /// and stays in the notification history (`N`)
+    pub fn push_notification(&mut self, message: &str) {
+        if self.notifications.len() == MAX_NOTIFICATIONS {
+            self.notifications.pop_front();
+        }
+        self.notifications.push_back((message.to_string(), Instant::now()));
+        self.dirty = true;
+    }
+
+    /// The newest notification while it is still shown in the status bar
+    pub fn current_notification(&self) -> Option<&str> {
+        self.notifications.back()
+            .filter(|(_, at)| at.elapsed() < NOTIFICATION_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// The latest status message
+    pub fn status(&self) -> &str {
+        self.status_log.back().map_or("", String::as_str)
+    }
+
+    /// Show a new status message; the previous ones stay in `status_log`
+    pub fn set_status(&mut self, message: String) {
+        if self.status_log.back() == Some(&message) {
+            return;
+        }
+        if self.status_log.len() == MAX_STATUS_LOG {
+            self.status_log.pop_front();
+        }
+        self.status_log.push_back(message);
+    }
+
+    /// Up to `count` messages shown before the latest one, oldest first
+    pub fn previous_statuses(&self, count: usize) -> impl Iterator<Item = &str> {
+        let end = self.status_log.len().saturating_sub(1);
+        self.status_log.range(end.saturating_sub(count)..end).map(String::as_str)
+    }
+
+    /// Grow the status bar by one line, back to one after MAX_STATUS_HEIGHT (`E`)
+    pub fn cycle_status_height(&mut self) {
+        self.status_height = self.status_height % MAX_STATUS_HEIGHT + 1;
+    }
+
+    /// Toggle the notification history overlay (`N`)
+    pub fn toggle_notifications(&mut self) {
+        self.show_notifications = !self.show_notifications;
+    }
+
+    /// "Artist — Title" from the tags of a track, or its file name when they're missing
+    fn track_label(&mut self, path: &Path) -> String {
+        if let Some(track) = self.current_cue_track().filter(|_| self.playing.as_deref() == Some(path)) {
+            return track.title.clone();
+        }
+        self.meta(path).display_title().unwrap_or_else(|| {
+            path.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+
+    /// True if the file lives in one of the configured podcast directories
+    fn is_podcast(&self, path: &Path) -> bool {
+        self.config.podcast_dirs.iter().any(|dir| path.starts_with(dir))
+    }
+
+    /// Skip the first `secs` seconds of the current track (podcast intros and ads)
+    pub fn skip_spoken_word_intro(&mut self, secs: u64) {
+        match self.seek_to(secs * 1000) {
+            Ok(()) => self.set_status(format!("Skipped intro: {}s", secs)),
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Path of the playlist written by `export_playlist`: `<dir name>.m3u` inside the directory
+    fn export_playlist_path(&self) -> PathBuf {
+        let name = self.current_dir.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "playlist".into());
+        self.current_dir.join(format!("{}.m3u", name))
+    }
+
+    /// Write the tracks of the current directory, in list order, to an M3U playlist
+    /// with absolute paths. Overwrites an existing file.
+    pub fn export_playlist(&self) -> io::Result<()> {
+        let mut contents = String::from("#EXTM3U\n");
+        for name in self.files.iter().filter(|f| is_track_entry(f)) {
+            contents.push_str(&self.current_dir.join(name).to_string_lossy());
+            contents.push('\n');
+        }
+        fs::write(self.export_playlist_path(), contents)
+    }
+
+    /// Export the playlist (`e`). An existing file is only overwritten when `e` is
+    /// pressed a second time in a row.
+    pub fn export_playlist_with_confirm(&mut self) {
+        let path = self.export_playlist_path();
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+
+        if path.exists() && !self.confirm_export_overwrite {
+            self.confirm_export_overwrite = true;
+            self.set_status(format!("{} already exists, press e again to overwrite", name));
+            return;
+        }
+        self.confirm_export_overwrite = false;
+
+        match self.export_playlist().and_then(|()| self.refresh_files()) {
+            Ok(()) => self.set_status(format!("Exported playlist: {}", name)),
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Queue the tracks of an M3U playlist and play the first. Entries that don't
+    /// exist on disk are skipped.
+    pub fn play_playlist(&mut self, playlist: &Path, progress_tx: &Sender<PlayerEvent>) -> io::Result<()> {
+        self.queue = playlist::parse_m3u(playlist)?
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect();
+
+        let count = self.queue.len();
+        match self.queue.pop_front() {
+            Some(first) => {
+                self.play_path(first, progress_tx, None);
+                self.set_status(format!("Playing playlist: {} tracks queued", count));
+            }
+            None => self.set_status("Playlist has no playable tracks".into()),
+        }
+        Ok(())
+    }
+
+    /// Queue every audio file of the current directory, in shuffle order while shuffle
+    /// is on and list order otherwise, and play the first (`:play all`)
+    pub fn play_all_in_dir(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        self.queue = if self.shuffle && !self.play_order.is_empty() {
+            self.play_order.iter().map(|&i| self.current_dir.join(&self.files[i])).collect()
+        } else {
+            self.listed_tracks().into_iter().filter(|path| !is_known_unsupported(path)).collect()
+        };
+
+        let count = self.queue.len();
+        match self.queue.pop_front() {
+            Some(first) => {
+                self.play_path(first, progress_tx, None);
+                self.set_status(format!("Playing directory: {} tracks queued", count));
+            }
+            None => self.set_status("No audio files in this directory".into()),
+        }
+    }
+
+    /// Play the next queued track, or the next file in the list when the queue is empty
+    pub fn advance(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        self.advance_with_fade(progress_tx, None);
+    }
+
+    /// Fade from the current track into the next one over `crossfade_secs`
+    pub fn crossfade_to_next(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        let fade = Duration::from_secs(self.crossfade_secs);
+        self.advance_with_fade(progress_tx, Some(fade));
+    }
+
+    fn advance_with_fade(&mut self, progress_tx: &Sender<PlayerEvent>, fade: Option<Duration>) {
+        if self.repeat == RepeatMode::One {
+            if let Some(track) = self.last_played.clone() {
+                self.play_path(track, progress_tx, fade);
+                return;
+            }
+        }
+        self.skip_to_next(progress_tx, fade);
+    }
+
+    /// Play the next track (`.`); unlike auto-advance this ignores repeat-one
+    pub fn play_next(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        if self.skip_cue_track(true) {
+            return;
+        }
+        self.skip_to_next(progress_tx, None);
+    }
+
+    fn skip_to_next(&mut self, progress_tx: &Sender<PlayerEvent>, fade: Option<Duration>) {
+        match self.next_track_path() {
+            Some(next) => self.play_path(next, progress_tx, fade),
+            None => self.set_status("End of list".into()),
+        }
+    }
+
+    /// Take the track to play next: the preloaded one, the head of the queue, else
+    /// the next file in the list
+    fn next_track_path(&mut self) -> Option<PathBuf> {
+        if let Some(next) = self.preloaded.take() {
+            Some(next)
+        } else if let Some(next) = self.queue.pop_front() {
+            Some(next)
+        } else if self.next_mp3() {
+            Some(self.current_dir.join(&self.files[self.selected]))
+        } else {
+            None
+        }
+    }
+
+    /// Append the track that follows the current one to the player, so it starts
+    /// without a gap. Does nothing if a track is already preloaded.
+    pub fn preload_next(&mut self) {
+        if self.preloaded.is_some() || self.playing.is_none() {
+            return;
+        }
+
+        let next = if self.repeat == RepeatMode::One {
+            self.last_played.clone()
+        } else {
+            self.next_track_path()
+        };
+        let Some(next) = next else {
+            return;
+        };
+
+        match self.player.enqueue_next(&next) {
+            Ok(()) => self.preloaded = Some(next),
+            Err(e) => {
+                // Leave the track for the regular advance at the end of the current one
+                if self.repeat != RepeatMode::One {
+                    self.queue.push_front(next);
+                }
+                self.push_notification(&format!("Error: {}", e));
+            }
+        }
+    }
+
+    /// Select a file given on the command line and play it, opening its directory
+    /// first when it is not the current one
+    pub fn play_on_start(&mut self, file: &Path, progress_tx: &Sender<PlayerEvent>) {
+        if let Some(parent) = file.parent().filter(|dir| *dir != self.current_dir) {
+            if let Err(e) = self.change_dir(parent.to_path_buf()) {
+                self.push_notification(&format!("Error: {}", e));
+                return;
+            }
+        }
+        self.wait_for_dir();
+
+        let name = file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        match self.files.iter().position(|f| *f == name) {
+            Some(index) => {
+                self.selected = index;
+                self.select(progress_tx);
+            }
+            None => self.set_status(format!("Not a playable file: {}", name)),
+        }
+    }
+
+    /// Start playing on launch, as set up in the `[autoplay]` table of the config
+    pub fn autoplay(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        if !self.config.autoplay.enabled {
+            return;
+        }
+
+        let last_played = self.last_played.clone()
+            .filter(|path| self.config.autoplay.mode == AutoplayMode::LastPlayed && path.is_file());
+        match last_played {
+            Some(path) => self.play_on_start(&path, progress_tx),
+            None => {
+                self.wait_for_dir();
+                let first = self.files.iter()
+                    .position(|f| is_track_entry(f) && is_audio_file(&self.current_dir.join(f)));
+                if let Some(index) = first {
+                    self.selected = index;
+                    self.select(progress_tx);
+                }
+            }
+        }
+
+        if self.playing.is_some() {
+            self.set_status(format!("[Autoplay] {}", self.status()));
+        }
+    }
+
+    /// Convenience: Call open_selected and update status if error
+    pub fn select(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        if let Err(e) = self.open_selected(progress_tx) {
+            self.push_notification(&format!("Error: {}", e));
+        }
+    }
+
+    pub fn set_progress_receiver(&mut self, rx: Receiver<PlayerEvent>) {
+        self.progress_rx = Some(rx);
+    }
+
+    pub fn poll_progress(&mut self) {
+        let mut finished_track = None;
+        let mut gapless_next = None;
+        let mut track_started = false;
+        let mut error = None;
+        let mut playback_finished = false;
+
+        // Bounded drain: anything left over is handled on the next tick
+        let events: Vec<PlayerEvent> = match &self.progress_rx {
+            Some(rx) => rx.try_iter().take(MAX_PROGRESS_MESSAGES_PER_TICK).collect(),
+            None => Vec::new(),
+        };
+        // Events of a track that was replaced or stopped since are stale
+        let generation = self.player.generation();
+        for event in events.into_iter().filter(|event| event.generation() == generation) {
+            self.dirty = true;
+            match event {
+                PlayerEvent::TrackEnded { .. } => {
+                    // Reset progress when the song finishes
+                    self.current_time = 0;
+                    self.total_time = 0;
+                    self.perc_played = 0.0;
+
+                    // Update status to indicate playback finished, unless it was stopped on purpose
+                    if let Some(finished) = self.playing.take() {
+                        // With a preloaded track the player already moved on to it
+                        match self.preloaded.take() {
+                            Some(next) => gapless_next = Some(next),
+                            None => {
+                                playback_finished = true;
+                                self.track_ended = true;
+                                self.update_now_playing();
+                            }
+                        }
+                        finished_track = Some(finished);
+                    }
+                }
+                PlayerEvent::Progress { elapsed, total, .. } => {
+                    track_started = true;
+                    // Count steady progress only; larger jumps are seeks
+                    let elapsed_ms = elapsed.as_millis() as u64;
+                    let played = elapsed_ms.saturating_sub(self.current_time);
+                    if played <= MAX_PLAYED_PER_PROGRESS_MS {
+                        self.session_time_ms += played;
+                    }
+                    self.current_time = elapsed_ms;
+                    self.total_time = total.as_millis() as u64;
+                    self.perc_played = if self.total_time > 0 {
+                        (self.current_time as f32 / self.total_time as f32) * 100.0
+                    } else {
+                        0.0
+                    };
+                }
+                PlayerEvent::SeekCompleted { position, .. } => self.current_time = position.as_millis() as u64,
+                PlayerEvent::BpmDetected { bpm, .. } => self.current_bpm = Some(bpm),
+                PlayerEvent::Error { error: e, .. } => {
+                    error = Some(e);
+                    self.playing = None;
+                    self.update_now_playing();
+                }
+            }
+        }
+
+        if playback_finished {
+            self.set_status("Playback finished".into());
+        }
+        if let Some(e) = error {
+            self.push_notification(&format!("Error: {}", e));
+        }
+        if let Some(finished) = finished_track {
+            self.on_track_end_hook(&finished);
+        }
+        if let Some(next) = gapless_next {
+            self.begin_track(next);
+        }
+
+        self.update_cue_track();
+
+        // The track reports progress, so it is playing and pending seeks can happen
+        if track_started {
+            if let Some(secs) = self.pending_intro_skip.take() {
+                self.skip_spoken_word_intro(secs);
+            }
+            if let Some(ms) = self.pending_resume.take() {
+                if let Err(e) = self.seek_to(ms) {
+                    self.push_notification(&format!("Error: {}", e));
+                }
+            }
+        }
+    }
+
+    /// Check every MP3 file in `dir` (the only ones with ID3 tags) for missing title, artist, album and track tags.
+    /// Only files with at least one missing field are returned, sorted by path.
+    pub fn scan_tag_quality(dir: &Path) -> io::Result<Vec<TagIssue>> {
+        let mut paths = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && tagread::has_id3_tags(path))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let issues = paths.into_iter()
+            .filter_map(|path| {
+                let tags = tagread::read_tags(&path);
+                let present = [
+                    tags.title.is_some(),
+                    tags.artist.is_some(),
+                    tags.album.is_some(),
+                    tags.track.is_some(),
+                ];
+                let missing_fields = LINT_FIELDS.iter()
+                    .zip(present)
+                    .filter(|(_, ok)| !ok)
+                    .map(|(field, _)| field.to_string())
+                    .collect::<Vec<_>>();
+
+                if missing_fields.is_empty() {
+                    None
+                } else {
+                    Some(TagIssue { path, missing_fields })
+                }
+            })
+            .collect();
+
+        Ok(issues)
+    }
+
+    /// Names of the files in the current directory that miss at least one tag field
+    pub fn files_with_missing_tags(&self) -> Vec<String> {
+        Self::scan_tag_quality(&self.current_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|issue| issue.path.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect()
+    }
+
+    /// Whether a track is playing, paused or there is none
+    pub fn playback_state(&self) -> PlaybackState {
+        if self.player.is_stopped() {
+            PlaybackState::Stopped
+        } else if self.player.is_paused() {
+            PlaybackState::Paused
+        } else {
+            PlaybackState::Playing
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.player.toggle_pause();
+
+        if self.player.is_paused() {
+            self.set_status("  PAUSED".into());
+        } else {
+            if let Some(path) = self.playing.clone() {
+                let label = self.track_label(&path);
+                self.set_status(format!("  Playing: {}", label));
+            } else {
+                self.set_status(String::new());
+            }
+        }
+
+        self.update_now_playing();
+    }
+
+    /// Stop playback and clear all session state, including the history log, then return
+    /// to the music directory (or the startup directory). The config on disk is not modified.
+    pub fn reset_to_defaults(&mut self) {
+        if let Err(e) = self.player.stop() {
+            self.push_notification(&format!("Error: {}", e));
+            return;
+        }
+        // Errors from here on belong to the fresh session
+        self.notifications.clear();
+        self.show_notifications = false;
+        self.playing = None;
+        self.preloaded = None;
+        self.update_now_playing();
+
+        self.current_time = 0;
+        self.total_time = 0;
+        self.perc_played = 0.0;
+        self.songs_played = 0;
+        self.queue.clear();
+        self.nav_stack.clear();
+        self.pending_intro_skip = None;
+        self.shuffle = false;
+        self.play_order.clear();
+        self.repeat = RepeatMode::Off;
+        self.history.clear();
+        self.show_history = false;
+        if self.config.save_history {
+            if let Err(e) = history::clear() {
+                self.push_notification(&format!("Error: {}", e));
+            }
+        }
+        self.apply_volume(self.config.default_volume);
+
+        let dir = self.music_dir();
+        match self.enter_dir(dir) {
+            Ok(()) => self.set_status("Reset to defaults".into()),
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Save the current equalizer gains as a named preset in the config file
+    pub fn save_eq_preset(&mut self, name: &str) {
+        self.config.eq_presets.insert(name.to_string(), self.eq.gains());
+        match self.config.save() {
+            Ok(()) => self.set_status(format!("Saved EQ preset: {}", name)),
+            Err(e) => self.push_notification(&format!("Error: {}", e)),
+        }
+    }
+
+    /// Load a saved (or built-in) EQ preset and restart the current track with it
+    pub fn load_eq_preset(&mut self, name: &str, progress_tx: &Sender<PlayerEvent>) {
+        let Some(gains) = self.config.eq_presets.get(name).cloned().or_else(|| eq::builtin_preset(name)) else {
+            self.set_status(format!("Unknown EQ preset: {}", name));
+            return;
+        };
+
+        self.eq = Equalizer::from_gains(&gains);
+        self.player.set_equalizer(self.eq.clone());
+
+        // The filter chain is built when a track starts, so restart at the same position
+        self.seek_to_current_offset(progress_tx);
+        self.set_status(format!("Loaded EQ preset: {}", name));
+    }
+
+    /// Open the `:` command prompt
+    pub fn open_command(&mut self) {
+        self.command = Some(String::new());
+    }
+
+    /// `S`: cancel the running sleep timer, or prompt for the minutes of a new one
+    pub fn toggle_sleep_timer(&mut self) {
+        if self.sleep_timer.take().is_some() {
+            self.set_status("Sleep timer cancelled".into());
+        } else {
+            self.command = Some("sleep ".into());
+        }
+    }
+
+    /// Time left until the sleep timer stops playback
+    pub fn sleep_remaining(&self) -> Option<Duration> {
+        self.sleep_timer.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Stop playback once the sleep timer runs out
+    pub fn check_sleep_timer(&mut self) {
+        if self.sleep_remaining() != Some(Duration::ZERO) {
+            return;
+        }
+        self.sleep_timer = None;
+        self.dirty = true;
+        if self.stop() {
+            self.set_status("Sleep timer: playback stopped".into());
+        }
+    }
+
+    /// Stop playback and clear the current track (`x`); nothing plays until a track
+    /// is chosen again. Returns false if the player could not be stopped; the error
+    /// is shown in the status bar.
+    pub fn stop(&mut self) -> bool {
+        let stopped = match self.player.stop() {
+            Ok(stopped) => stopped,
+            Err(e) => {
+                self.push_notification(&format!("Error: {}", e));
+                return false;
+            }
+        };
+        // Progress the stopped track reported before it stopped would bring the bar back
+        if let Some(rx) = &self.progress_rx {
+            rx.try_iter().for_each(drop);
+        }
+        self.playing = None;
+        self.requeue_preloaded();
+        self.current_time = 0;
+        self.total_time = 0;
+        self.perc_played = 0.0;
+        self.update_now_playing();
+        self.set_status(if stopped { "Stopped" } else { "Nothing is playing" }.into());
+        true
+    }
+
+    /// Accept commands from `empitrio --ctl`. A second instance runs without it.
+    #[cfg(unix)]
+    pub fn start_ipc(&mut self) -> Option<ipc::SocketGuard> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        match ipc::start_ipc_server(tx) {
+            Ok(guard) => {
+                self.ipc_rx = Some(rx);
+                Some(guard)
+            }
+            Err(e) => {
+                self.set_status(format!("Remote control unavailable: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Run the commands received from `empitrio --ctl`
+    #[cfg(unix)]
+    pub fn poll_ipc(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        use ipc::IpcCommand;
+
+        let Some(commands) = self.ipc_rx.as_ref().map(|rx| rx.try_iter().collect::<Vec<_>>()) else {
+            return;
+        };
+        self.dirty |= !commands.is_empty();
+        for command in commands {
+            match command {
+                IpcCommand::PlayPause if self.playing.is_some() => self.pause(),
+                IpcCommand::PlayPause => self.select(progress_tx),
+                IpcCommand::Next => self.play_next(progress_tx),
+                IpcCommand::Previous => self.play_previous(progress_tx),
+                IpcCommand::Stop => {
+                    self.stop();
+                }
+                IpcCommand::SetVolume(volume) => self.set_volume(volume),
+                IpcCommand::QueueFile(file) => {
+                    let path = PathBuf::from(file);
+                    self.set_status(format!("Queued: {}", path.file_name().unwrap_or_default().to_string_lossy()));
+                    self.queue.push_back(path);
+                }
+            }
+        }
+    }
+
+    /// Register the MPRIS2 service; without a session bus the player works as usual
+    #[cfg(feature = "mpris")]
+    pub fn start_mpris(&mut self) {
+        match mpris::Mpris::start() {
+            Ok(service) => self.mpris = Some(service),
+            Err(e) => self.set_status(format!("MPRIS unavailable: {}", e)),
+        }
+    }
+
+    /// Run the commands of D-Bus clients and publish the current state to them
+    #[cfg(feature = "mpris")]
+    pub fn poll_mpris(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        use mpris::MprisCommand;
+
+        let Some(commands) = self.mpris.as_ref().map(|service| service.commands()) else {
+            return;
+        };
+        self.dirty |= !commands.is_empty();
+        for command in commands {
+            match command {
+                MprisCommand::PlayPause if self.playing.is_some() => self.pause(),
+                MprisCommand::Play | MprisCommand::PlayPause => {
+                    if self.playing.is_none() {
+                        self.select(progress_tx);
+                    } else if self.player.is_paused() {
+                        self.pause();
+                    }
+                }
+                MprisCommand::Pause => {
+                    if self.playing.is_some() && !self.player.is_paused() {
+                        self.pause();
+                    }
+                }
+                MprisCommand::Next => self.play_next(progress_tx),
+                MprisCommand::Previous => self.play_previous(progress_tx),
+                MprisCommand::Stop => {
+                    self.stop();
+                }
+                MprisCommand::Seek(offset_us) => {
+                    let mut target = (self.current_time as i64 + offset_us / 1000).max(0) as u64;
+                    if self.total_time > 0 {
+                        target = target.min(self.total_time);
+                    }
+                    self.seek_relative(target);
+                }
+                MprisCommand::SetPosition(position_us) => {
+                    self.seek_relative((position_us / 1000).max(0) as u64);
+                }
+            }
+        }
+
+        if let Some(service) = &self.mpris {
+            service.update(
+                self.playing.as_deref(),
+                self.songs_played,
+                self.player.is_paused(),
+                self.current_time,
+                self.total_time,
+                self.volume,
+            );
+            service.update_rate(self.speed);
+        }
+    }
+
+    /// Run the command typed at the `:` prompt and close it
+    pub fn run_command(&mut self, progress_tx: &Sender<PlayerEvent>) {
+        let Some(command) = self.command.take() else {
+            return;
+        };
+
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["reset"] => self.reset_to_defaults(),
+            // Ctrl+I can't be used: terminals send it as Tab
+            ["stats"] => self.toggle_stats(),
+            // Terminals send Ctrl+Enter as a plain Enter, so this has no key of its own
+            ["play", "all"] => self.play_all_in_dir(progress_tx),
+            ["refresh", "library"] => {
+                self.compute_library_size();
+                self.set_status("Scanning library...".into());
+            }
+            ["sort", "name"] => self.set_sort(SortBy::Name),
+            ["sort", "name-desc"] => self.set_sort(SortBy::NameDesc),
+            ["sort", "duration"] => self.set_sort(SortBy::Duration),
+            ["sort", "size"] => self.set_sort(SortBy::Size),
+            ["sort", "modified"] => self.set_sort(SortBy::Modified),
+            ["sort", "bpm"] => self.set_sort(SortBy::Bpm),
+            ["eq", "save", name] => self.save_eq_preset(name),
+            ["eq", "load", name] => self.load_eq_preset(name, progress_tx),
+            ["sleep", minutes] => match minutes.parse::<u64>() {
+                Ok(minutes) if minutes > 0 => {
+                    self.sleep_timer = Some(Instant::now() + Duration::from_secs(minutes * 60));
+                    self.set_status(format!("Sleep timer set for {} min", minutes));
+                }
+                _ => self.set_status("Sleep timer needs a number of minutes".into()),
+            },
+            ["crossfade"] => {
+                self.crossfade_enabled = !self.crossfade_enabled;
+                self.set_status(format!(
+                    "Crossfade {}",
+                    if self.crossfade_enabled { "on" } else { "off" }
+                ));
+            }
+            ["crossfade", secs] => match secs.parse::<u64>() {
+                Ok(secs) if CROSSFADE_SECS_RANGE.contains(&secs) => {
+                    self.crossfade_secs = secs;
+                    self.crossfade_enabled = true;
+                    self.set_status(format!("Crossfade on ({}s)", secs));
+                }
+                _ => self.set_status("Crossfade length must be 1 to 5 seconds".into()),
+            },
+            ["gapless"] => {
+                self.gapless_enabled = !self.gapless_enabled;
+                self.player.set_gapless(self.gapless_enabled);
+                self.set_status(format!(
+                    "Gapless playback {}",
+                    if self.gapless_enabled { "on" } else { "off" }
+                ));
+            }
+            ["replaygain"] => {
+                self.replay_gain_enabled = !self.replay_gain_enabled;
+                self.player.set_replay_gain(self.replay_gain_enabled);
+                self.set_status(format!(
+                    "ReplayGain {}",
+                    if self.replay_gain_enabled { "on" } else { "off" }
+                ));
+            }
+            _ => self.set_status(format!("Unknown command: {}", command.trim())),
+        }
+    }
+
+    /// Write the now-playing line for the current track to `path`, or an empty
+    /// file when nothing is playing. The write is atomic (temp file + rename).
+    pub fn write_now_playing_file(&self, path: &Path) -> io::Result<()> {
+        let line = match &self.playing {
+            Some(file) => {
+                let tags = tagread::read_tags(file);
+                let stem = file.file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let state = if self.player.is_paused() { "paused" } else { "playing" };
+
+                self.config.now_playing_format
+                    .replace("{artist}", tags.artist.as_deref().unwrap_or("Unknown Artist"))
+                    .replace("{title}", tags.title.as_deref().unwrap_or(&stem))
+                    .replace("{album}", tags.album.as_deref().unwrap_or(""))
+                    .replace("{file}", &stem)
+                    .replace("{state}", state)
+            }
+            None => String::new(),
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, line)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Look for an album art image (cover.jpg, folder.jpg, ...) in `dir`, then one
+    /// level up for albums split into per-disc subdirectories.
+    /// Names are matched case-insensitively, so `Folder.jpg` is found as well.
+    pub fn detect_album_art_file(dir: &Path) -> Option<PathBuf> {
+        std::iter::once(dir)
+            .chain(dir.parent())
+            .find_map(find_album_art_in)
+    }
+
+    /// Refresh the now-playing file if one is configured
+    fn update_now_playing(&mut self) {
+        if let Some(path) = &self.config.now_playing_file {
+            if let Err(e) = self.write_now_playing_file(Path::new(path)) {
+                self.push_notification(&format!("Error: now-playing file: {}", e));
+            }
+        }
+    }
+}
+
+/// Last path component of a directory, or the whole path for a root
+pub fn dir_basename(dir: &Path) -> String {
+    dir.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| dir.to_string_lossy().into_owned())
+}
+
+/// Byte range of the first case-insensitive occurrence of `needle` in `haystack`
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<Range<usize>> {
+    if needle.is_empty() {
+        return Some(0..0);
+    }
+    haystack.char_indices().find_map(|(start, _)| {
+        let mut rest = haystack[start..].char_indices();
+        let mut end = start;
+        for wanted in needle.chars() {
+            let (offset, c) = rest.next()?;
+            if !c.to_lowercase().eq(wanted.to_lowercase()) {
+                return None;
+            }
+            end = start + offset + c.len_utf8();
+        }
+        Some(start..end)
+    })
+}
+
+/// List order by name: "..." first, then folders, then files, case-insensitively.
+/// Names differing only in case keep a fixed order, so sorted lists can be merged.
+fn compare_by_name(a: &str, b: &str) -> Ordering {
+    let a_is_up = a == "...";
+    let b_is_up = b == "...";
+    let a_is_dir = a.ends_with('/');
+    let b_is_dir = b.ends_with('/');
+    b_is_up.cmp(&a_is_up)
+        .then(b_is_dir.cmp(&a_is_dir))
+        .then(a.to_lowercase().cmp(&b.to_lowercase()))
+        .then_with(|| a.cmp(b))
+}
+
+/// Where an entry goes in any sort order: "..." first, then folders, then files
+fn list_group(name: &str) -> u8 {
+    if name == "..." {
+        0
+    } else if name.ends_with('/') {
+        1
+    } else {
+        2
+    }
+}
+
+/// Sort key of a list entry in name order: not "...", not a folder, lowercased name, name
+type NameKey = (bool, bool, String, String);
+
+/// `compare_by_name` as a key, for sorting long lists without lowercasing every
+/// name again on each comparison
+fn name_sort_key(name: &str) -> NameKey {
+    (name != "...", !name.ends_with('/'), name.to_lowercase(), name.to_string())
+}
+
+/// Path of the `.listened` marker for an audio file (e.g. `episode1.mp3.listened`)
+fn listened_marker(path: &Path) -> PathBuf {
+    let mut marker = path.as_os_str().to_owned();
+    marker.push(".listened");
+    PathBuf::from(marker)
+}
+
+/// Return the first album art image found in `dir`, if any
+fn find_album_art_in(dir: &Path) -> Option<PathBuf> {
+    let names: Vec<String> = fs::read_dir(dir).ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+
+    ALBUM_ART_NAMES.iter().find_map(|wanted| {
+        names.iter()
+            .find(|name| name.eq_ignore_ascii_case(wanted))
+            .map(|name| dir.join(name))
+            .filter(|path| path.is_file())
+    })
+}
+
+/// Owns the terminal while the TUI runs and restores it when dropped, so raw mode
+/// and the alternate screen are left even if the UI loop panics or bails out early.
+struct TerminalGuard<B: Backend + Write> {
+    terminal: Terminal<B>,
+}
+
+impl<B: Backend + Write> TerminalGuard<B> {
+    fn new(backend: B) -> io::Result<Self> {
+        Ok(Self { terminal: Terminal::new(backend)? })
+    }
+}
+
+impl<B: Backend + Write> Drop for TerminalGuard<B> {
+    fn drop(&mut self) {
+        // Best effort: there is nobody left to report errors to
+        let _ = disable_raw_mode();
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture);
+        let _ = self.terminal.show_cursor();
+    }
+}
+
+/// Leave raw mode, the alternate screen and mouse capture where the `TerminalGuard`
+/// doesn't get to: before a panic message is printed
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, Show);
+}
+
+fn main() -> io::Result<()> {
+    // Subcommands run without the TUI
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("lint") {
+        let code = lint::run(&args[1..])?;
+        std::process::exit(code);
+    }
+    if args.first().map(String::as_str) == Some("--benchmark") {
+        let code = benchmark::run(&args[1..])?;
+        std::process::exit(code);
+    }
+    #[cfg(unix)]
+    if args.first().map(String::as_str) == Some("--ctl") {
+        let code = ipc::run_ctl(&args[1..])?;
+        std::process::exit(code);
+    }
+
+    // Optional start directory (`empitrio ~/Music`) and track to play right away (`--play <file>`)
+    let usage = "Usage: empitrio [<dir>] [--play <file>] [--no-autoplay] [--no-restore-queue] | lint [<dir>] | --benchmark [--dir <path>] | --ctl <command>";
+    let mut start_dir = None;
+    let mut play_on_start = None;
+    let mut no_autoplay = false;
+    let mut no_restore_queue = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--play" => {
+                let Some(file) = args.next().map(PathBuf::from) else {
+                    eprintln!("empitrio: --play needs a file");
+                    eprintln!("{usage}");
+                    std::process::exit(2);
+                };
+                if !file.is_file() {
+                    let reason = if file.exists() { "not a file" } else { "no such file" };
+                    eprintln!("empitrio: {}: {}", file.display(), reason);
+                    std::process::exit(2);
+                }
+                play_on_start = Some(file.canonicalize()?);
+            }
+            "--no-autoplay" => no_autoplay = true,
+            "--no-restore-queue" => no_restore_queue = true,
+            other if other.starts_with('-') => {
+                eprintln!("empitrio: unknown option: {other}");
+                eprintln!("{usage}");
+                std::process::exit(2);
+            }
+            other => {
+                let dir = PathBuf::from(other);
+                if !dir.is_dir() {
+                    let reason = if dir.exists() { "not a directory" } else { "no such directory" };
+                    eprintln!("empitrio: {}: {}", dir.display(), reason);
+                    eprintln!("{usage}");
+                    std::process::exit(2);
+                }
+                // Relative paths would break going up with "..."
+                start_dir = Some(dir.canonicalize()?);
+            }
+        }
+    }
+
+    // Create a channel for playback progress and other player events
+    let (progress_tx, progress_rx) = std::sync::mpsc::channel::<PlayerEvent>();
+
+    // The panic message would be lost on the alternate screen, so restore the terminal
+    // first. SIGINT (e.g. `kill -INT`; Ctrl+C is a key press in raw mode) asks the UI loop
+    // to quit, so the usual shutdown below still runs before exiting with 130.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let on_sigint = Arc::clone(&interrupted);
+    ctrlc::set_handler(move || on_sigint.store(true, AtomicOrdering::Relaxed))
+        .map_err(io::Error::other)?;
+
+    // Set up terminal
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let mut guard = TerminalGuard::new(CrosstermBackend::new(stdout))?;
+
+    // Initialize app with the audio player and give it the receiver side of the channel.
+    // The command line wins over `startup_dir` in the config, which wins over the last session.
+    let player = Player::new();
+    let mut app = match start_dir {
+        Some(dir) => App::new_at_dir(dir, player)?,
+        None => App::new(player)?,
+    };
+    app.set_progress_receiver(progress_rx);
+    if !no_restore_queue {
+        app.restore_queue();
+    }
+    #[cfg(feature = "mpris")]
+    app.start_mpris();
+    // Remote control through `empitrio --ctl`; the guard removes the socket on exit
+    #[cfg(unix)]
+    let _ipc_guard = app.start_ipc();
+    if let Some(file) = play_on_start {
+        app.play_on_start(&file, &progress_tx);
+    } else if !no_autoplay {
+        app.autoplay(&progress_tx);
+    }
+
+    // Run the UI loop passing terminal, app, and the sender
+    let result = ui_loop(&mut guard.terminal, &mut app, progress_tx, &interrupted);
+
+    // Restore terminal
+    drop(guard);
+
+    // Nothing is playing once we exit
+    app.playing = None;
+    app.update_now_playing();
+
+    if let Err(e) = app.save_state() {
+        eprintln!("{e}");
+    }
+    if let Err(e) = app.save_queue() {
+        eprintln!("Failed to save the queue: {e}");
+    }
+
+    // `exit` skips destructors, so remove the IPC socket first
+    if interrupted.load(AtomicOrdering::Relaxed) {
+        #[cfg(unix)]
+        drop(_ipc_guard);
+        std::process::exit(130);
+    }
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::{TerminalOptions, Viewport};
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    /// A screen that records what was written to it. With `fail_first_flush`, the
+    /// first frame can't be shown, like a terminal that went away mid-draw.
+    #[derive(Clone, Default)]
+    struct Screen {
+        out: Rc<RefCell<Vec<u8>>>,
+        flushes: Rc<Cell<usize>>,
+        fail_first_flush: bool,
+    }
+
+    impl Write for Screen {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.out.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes.set(self.flushes.get() + 1);
+            if self.fail_first_flush && self.flushes.get() == 1 {
+                return Err(io::Error::other("screen is gone"));
+            }
+            Ok(())
+        }
+    }
+
+    /// An app over an empty listing that never plays anything on its own
+    fn test_app() -> App {
+        test_app_at(env::temp_dir())
+    }
+
+    /// An app over `dir` with default settings, leaving the user's config, state
+    /// and the audio device alone
+    fn test_app_at(dir: PathBuf) -> App {
+        App::with_settings(dir, Player::new(), Config::default(), State::default(), Bookmarks::default())
+            .unwrap()
+    }
+
+    /// Run the UI loop on `screen` the way `main` does and return the loop's result
+    /// along with everything written to the screen, including the guard's cleanup
+    fn run_ui(screen: &Screen, interrupted: bool) -> (io::Result<()>, String) {
+        let viewport = Viewport::Fixed(Rect::new(0, 0, 80, 24));
+        let terminal = Terminal::with_options(CrosstermBackend::new(screen.clone()), TerminalOptions { viewport }).unwrap();
+        let mut guard = TerminalGuard { terminal };
+        let mut app = test_app();
+        let (progress_tx, _progress_rx) = std::sync::mpsc::channel();
+
+        let result = ui_loop(&mut guard.terminal, &mut app, progress_tx, &AtomicBool::new(interrupted));
+        drop(guard);
+        (result, String::from_utf8_lossy(&screen.out.borrow()).into_owned())
+    }
+
+    fn assert_restored(output: &str) {
+        let leave = output.rfind("\x1b[?1049l").expect("alternate screen left");
+        assert!(output[leave..].contains("\x1b[?25h"), "cursor shown again");
+        assert!(output.contains("\x1b[?1000l"), "mouse capture disabled");
+    }
+
+    #[test]
+    fn terminal_is_restored_when_the_ui_loop_fails() {
+        let screen = Screen { fail_first_flush: true, ..Screen::default() };
+        let (result, output) = run_ui(&screen, false);
+        assert!(result.is_err());
+        assert_restored(&output);
+    }
+
+    #[test]
+    fn sigint_quits_the_ui_loop_and_restores_the_terminal() {
+        let (result, output) = run_ui(&Screen::default(), true);
+        assert!(result.is_ok());
+        assert_restored(&output);
+    }
+
+    #[test]
+    fn poll_progress_handles_a_bounded_batch_per_call() {
+        let mut app = test_app();
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.set_progress_receiver(rx);
+        for secs in 0..100 {
+            let progress = PlayerEvent::Progress {
+                generation: app.player.generation(),
+                elapsed: Duration::from_secs(secs),
+                total: Duration::from_secs(200),
+            };
+            tx.send(progress).unwrap();
+        }
+
+        // The last event of each batch is the position shown
+        app.poll_progress();
+        assert_eq!(app.current_time, 9_000);
+        app.poll_progress();
+        assert_eq!(app.current_time, 19_000);
+        for _ in 0..8 {
+            app.poll_progress();
+        }
+        assert_eq!(app.current_time, 99_000);
+        assert_eq!(app.progress_rx.as_ref().unwrap().try_iter().count(), 0);
+    }
+
+    #[test]
+    fn poll_progress_drops_events_of_a_stopped_track() {
+        let mut app = test_app();
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.set_progress_receiver(rx);
+        let stale = app.player.generation();
+        app.player.stop().unwrap();
+
+        let notifications = app.notifications.len();
+        let progress = PlayerEvent::Progress { generation: stale, elapsed: Duration::from_secs(5), total: Duration::from_secs(200) };
+        tx.send(progress).unwrap();
+        tx.send(PlayerEvent::Error { generation: stale, error: PlayerError::NothingPlaying }).unwrap();
+        app.poll_progress();
+        assert_eq!(app.current_time, 0);
+        assert_eq!(app.total_time, 0);
+        assert_eq!(app.notifications.len(), notifications);
+    }
+
+    #[test]
+    fn next_and_previous_skip_formats_that_cant_be_played() {
+        let mut app = test_app();
+        app.files = ["...", "a.mp3", "b.opus", "c.mp3"].map(String::from).to_vec();
+        app.selected = 1;
+        assert!(app.next_mp3());
+        assert_eq!(app.selected, 3);
+        assert!(app.previous_mp3());
+        assert_eq!(app.selected, 1);
+    }
+
+    #[test]
+    fn refresh_files_merges_changes_into_the_list() {
+        let dir = env::temp_dir().join(format!("empitrio-refresh-{}", std::process::id()));
+        fs::create_dir_all(dir.join("Album")).unwrap();
+        for name in ["b.mp3", "D.mp3"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let mut app = test_app_at(dir.clone());
+        app.wait_for_dir();
+        app.selected = app.files.iter().position(|f| f == "D.mp3").unwrap();
+
+        fs::write(dir.join("a.mp3"), b"").unwrap();
+        fs::write(dir.join("c.mp3"), b"").unwrap();
+        fs::remove_file(dir.join("b.mp3")).unwrap();
+        app.refresh_files().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(app.files, ["...", "Album/", "a.mp3", "c.mp3", "D.mp3"]);
+        assert_eq!(app.files[app.selected], "D.mp3");
+    }
+
+    #[test]
+    fn format_duration_below_and_above_an_hour() {
+        assert_eq!(App::format_duration(0), "--:--");
+        assert_eq!(App::format_duration(1), "00:01");
+        assert_eq!(App::format_duration(59), "00:59");
+        assert_eq!(App::format_duration(3600), "1:00:00");
+        assert_eq!(App::format_duration(7384), "2:03:04");
+        assert_eq!(App::format_duration(u64::MAX), "5124095576030431:00:15");
+    }
+
+    #[test]
+    fn remaining_secs_mid_track_rounds_up() {
+        let mut app = test_app();
+        app.total_time = 245_000;
+        app.current_time = 83_000;
+        assert_eq!(app.remaining_secs(), Some(162));
+        app.current_time = 83_400;
+        assert_eq!(app.remaining_secs(), Some(162));
+        app.current_time = 245_000;
+        assert_eq!(app.remaining_secs(), Some(0));
+    }
+
+    #[test]
+    fn remaining_secs_is_unknown_without_a_duration() {
+        let mut app = test_app();
+        app.total_time = 0;
+        app.current_time = 0;
+        assert_eq!(app.remaining_secs(), None);
+        app.current_time = 5_000;
+        assert_eq!(app.remaining_secs(), None);
+    }
+
+    #[test]
+    fn remaining_secs_does_not_underflow_past_the_end() {
+        let mut app = test_app();
+        app.total_time = 10_000;
+        app.current_time = 10_500;
+        assert_eq!(app.remaining_secs(), None);
+    }
+}
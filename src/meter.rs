@@ -0,0 +1,83 @@
+// ============================================================================
+// em(π)trio MP3 Player — meter.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Level metering for the visualizer: a rodio `Source` wrapper that passes the
+// samples through unchanged and sends the RMS level of every short window to
+// the UI.
+// ============================================================================
+
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Levels sent per second of audio
+const LEVELS_PER_SEC: u32 = 20;
+
+pub struct MeteringSource<S> {
+    inner: S,
+    levels: Option<Sender<f32>>, // None once the receiver is gone (or when metering is off)
+    window: usize,               // Samples per level, over all channels
+    count: usize,
+    sum_squares: f32,
+}
+
+impl<S: Source<Item = f32>> MeteringSource<S> {
+    pub fn new(inner: S, levels: Option<Sender<f32>>) -> Self {
+        let window = (inner.sample_rate() * inner.channels() as u32 / LEVELS_PER_SEC).max(1) as usize;
+        Self { inner, levels, window, count: 0, sum_squares: 0.0 }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for MeteringSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+
+        if let Some(levels) = &self.levels {
+            self.sum_squares += sample * sample;
+            self.count += 1;
+            if self.count == self.window {
+                let rms = (self.sum_squares / self.count as f32).sqrt();
+                if levels.send(rms).is_err() {
+                    self.levels = None;
+                }
+                self.count = 0;
+                self.sum_squares = 0.0;
+            }
+        }
+
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for MeteringSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+        self.count = 0;
+        self.sum_squares = 0.0;
+        Ok(())
+    }
+}
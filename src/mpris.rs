@@ -0,0 +1,331 @@
+// ============================================================================
+// em(π)trio MP3 Player — mpris.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// MPRIS2 D-Bus interface (`--features mpris`), so desktop media controls and
+// tools like `playerctl` can see and control empitrio. D-Bus calls arrive on
+// zbus' own thread and are passed to the UI loop as `MprisCommand`s; the UI
+// loop reports its state back through `Mpris::update`.
+// ============================================================================
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use zbus::blocking::{connection, Connection};
+use zbus::interface;
+use zbus::zvariant::{ObjectPath, Value};
+
+use crate::tagread;
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.empitrio";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Requests from D-Bus clients, handled by the UI loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MprisCommand {
+    PlayPause,
+    Play,
+    Pause,
+    Next,
+    Previous,
+    Stop,
+    Seek(i64),        // Relative offset, in microseconds
+    SetPosition(i64), // Absolute position, in microseconds
+}
+
+/// What the player is doing, as published on the bus
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PlayerState {
+    status: &'static str,       // "Playing", "Paused" or "Stopped"
+    track: Option<PathBuf>,     // Current track, if any
+    track_number: usize,        // Tracks started so far; makes the MPRIS track id unique
+    title: String,
+    artist: String,
+    album: String,
+    length_us: i64,
+    position_us: i64,
+    volume: f64,
+    rate: f64,                  // Playback speed (1.0 = normal)
+}
+
+impl PlayerState {
+    fn metadata(&self) -> HashMap<String, Value<'static>> {
+        let mut metadata = HashMap::new();
+        let track_id = match self.track {
+            Some(_) => format!("/org/empitrio/track/{}", self.track_number),
+            None => "/org/mpris/MediaPlayer2/TrackList/NoTrack".into(),
+        };
+        if let Ok(path) = ObjectPath::try_from(track_id) {
+            metadata.insert("mpris:trackid".into(), Value::from(path));
+        }
+        if self.track.is_some() {
+            metadata.insert("mpris:length".into(), Value::from(self.length_us));
+            metadata.insert("xesam:title".into(), Value::from(self.title.clone()));
+            metadata.insert("xesam:artist".into(), Value::from(vec![self.artist.clone()]));
+            metadata.insert("xesam:album".into(), Value::from(self.album.clone()));
+        }
+        metadata
+    }
+}
+
+/// `org.mpris.MediaPlayer2`: identifies the player
+struct Root;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "em(π)trio"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec!["file".into()]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        vec!["audio/mpeg".into(), "audio/flac".into(), "audio/ogg".into(), "audio/wav".into()]
+    }
+}
+
+/// `org.mpris.MediaPlayer2.Player`: playback control and state
+struct Player {
+    commands: Sender<MprisCommand>,
+    state: Arc<Mutex<PlayerState>>,
+}
+
+impl Player {
+    fn send(&self, command: MprisCommand) {
+        // The UI loop is gone while the app shuts down; nothing left to control
+        let _ = self.commands.send(command);
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, PlayerState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(MprisCommand::Next);
+    }
+
+    fn previous(&self) {
+        self.send(MprisCommand::Previous);
+    }
+
+    fn pause(&self) {
+        self.send(MprisCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(MprisCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.send(MprisCommand::Stop);
+    }
+
+    fn play(&self) {
+        self.send(MprisCommand::Play);
+    }
+
+    fn seek(&self, offset: i64) {
+        self.send(MprisCommand::Seek(offset));
+    }
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, position: i64) {
+        self.send(MprisCommand::SetPosition(position));
+    }
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        self.state().status.into()
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, Value<'static>> {
+        self.state().metadata()
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+        self.state().position_us
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.state().volume
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.state().rate
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        f64::from(*crate::SPEED_RANGE.start())
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        f64::from(*crate::SPEED_RANGE.end())
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// The registered MPRIS service; dropping it leaves the bus
+pub struct Mpris {
+    connection: Connection,
+    state: Arc<Mutex<PlayerState>>,
+    commands: Receiver<MprisCommand>,
+}
+
+impl Mpris {
+    /// Register `org.mpris.MediaPlayer2.empitrio` on the session bus
+    pub fn start() -> zbus::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let state = Arc::new(Mutex::new(PlayerState { status: "Stopped", rate: 1.0, ..PlayerState::default() }));
+        let player = Player { commands: tx, state: state.clone() };
+
+        let connection = connection::Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, Root)?
+            .serve_at(OBJECT_PATH, player)?
+            .build()?;
+
+        Ok(Self { connection, state, commands: rx })
+    }
+
+    /// Commands received since the last call
+    pub fn commands(&self) -> Vec<MprisCommand> {
+        self.commands.try_iter().collect()
+    }
+
+    /// Publish the current state. Clients are notified when the playback status,
+    /// the track or the volume change; the position is only read on demand.
+    pub fn update(&self, track: Option<&Path>, track_number: usize, paused: bool, position_ms: u64, length_ms: u64, volume: f32) {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = state.clone();
+
+        state.status = match (track, paused) {
+            (None, _) => "Stopped",
+            (Some(_), true) => "Paused",
+            (Some(_), false) => "Playing",
+        };
+        state.position_us = position_ms as i64 * 1000;
+        state.length_us = length_ms as i64 * 1000;
+        state.volume = volume as f64;
+        state.track_number = track_number;
+
+        // Tags are only read when the track changes
+        if state.track.as_deref() != track {
+            state.track = track.map(Path::to_path_buf);
+            let tags = track.map(tagread::read_tags).unwrap_or_default();
+            let stem = track
+                .and_then(Path::file_stem)
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            state.title = tags.title.unwrap_or(stem);
+            state.artist = tags.artist.unwrap_or_default();
+            state.album = tags.album.unwrap_or_default();
+        }
+
+        let mut changed: HashMap<&str, Value> = HashMap::new();
+        if state.status != previous.status {
+            changed.insert("PlaybackStatus", Value::from(state.status));
+        }
+        if state.track != previous.track || state.track_number != previous.track_number || state.length_us != previous.length_us {
+            changed.insert("Metadata", Value::from(state.metadata()));
+        }
+        if state.volume != previous.volume {
+            changed.insert("Volume", Value::from(state.volume));
+        }
+        drop(state);
+        self.notify(changed);
+    }
+
+    /// Publish the playback speed, notifying clients when it changed
+    pub fn update_rate(&self, speed: f32) {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let rate = f64::from(speed);
+        if state.rate == rate {
+            return;
+        }
+        state.rate = rate;
+        drop(state);
+        self.notify(HashMap::from([("Rate", Value::from(rate))]));
+    }
+
+    /// Tell clients which properties changed, if any
+    fn notify(&self, changed: HashMap<&str, Value>) {
+        if !changed.is_empty() {
+            let _ = self.connection.emit_signal(
+                None::<()>,
+                OBJECT_PATH,
+                "org.freedesktop.DBus.Properties",
+                "PropertiesChanged",
+                &(PLAYER_INTERFACE, changed, Vec::<&str>::new()),
+            );
+        }
+    }
+}
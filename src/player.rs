@@ -1,7 +1,6 @@
 // ============================================================================
 // em(π)trio MP3 Player — player.rs
 // Non-blocking MP3 playback with rodio — ensures only ONE track plays at a time.
-// Requires: once_cell = "1" in Cargo.toml
 // Author: Tom Papatolis
 // Email: tom@tpapatolis.com
 // Github: https://github.com/tomgineer/empitrio
@@ -10,115 +9,741 @@
 // to play one track at a time and sending playback progress updates.
 // ============================================================================
 
-use rodio::{Decoder, OutputStream, Sink, Source};
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::decoder::DecoderError;
+use rodio::source::SeekError;
+use rodio::{cpal, source::Zero, Decoder, OutputStream, OutputStreamHandle, PlayError, Sink, Source, StreamError};
 use std::{
+    error::Error,
+    fmt,
     fs::File,
-    io::BufReader,
+    io::{self, BufReader, Read, Seek},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, TryLockError,
+    },
     thread,
 };
-use once_cell::sync::Lazy;
+
+use crate::analysis;
+use crate::eq::{EqSource, Equalizer};
+use crate::meter::MeteringSource;
+use crate::stream::HttpStream;
+use crate::tagread;
 
 use std::sync::mpsc::Sender;
 use std::time::{Instant, Duration};
 
-// Global sink handle guarded by a mutex so we can stop the previous song
-static CURRENT_SINK: Lazy<Mutex<Option<Arc<Sink>>>> = Lazy::new(|| Mutex::new(None));
+// How long the public API waits for the player state before giving up
+const LOCK_TIMEOUT: Duration = Duration::from_millis(50);
 
-/// Toggle pause/resume of the current playing sink, if any.
-pub fn toggle_pause() {
-    let sink_guard = CURRENT_SINK.lock().expect("Failed to lock CURRENT_SINK");
-    if let Some(sink) = sink_guard.as_ref() {
-        if sink.is_paused() {
-            sink.play();
-        } else {
-            sink.pause();
+// How often the progress thread reports the position of the playing track
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+// How much of a track the tempo is estimated from
+const BPM_ANALYSIS_DURATION: Duration = Duration::from_secs(30);
+
+/// Settings and the current sink, shared between `Player` handles and the playback threads
+#[derive(Default)]
+struct PlayerInner {
+    current_sink: Option<Arc<Sink>>, // Sink of the current track, so it can be stopped
+    volume: f32,                     // Applied to every new sink (1.0 = 100%)
+    speed: f32,                      // Applied to every new sink (1.0 = normal speed)
+    device_name: Option<String>,     // Output device chosen in the device picker; None plays on the system default
+    equalizer: Equalizer,            // Applied to every new track
+    level_sender: Option<Sender<f32>>, // Where every new track sends its signal levels, for the visualizer
+    preloaded_total: Option<Duration>, // Length of the track appended by `enqueue_next`, taken by the progress thread
+    replay_gain: bool,               // Scale the volume by each track's ReplayGain
+    track_gain: Option<f32>,         // Linear ReplayGain factor of the current track, if it has one
+    preloaded_gain: Option<f32>,     // The same for the track appended by `enqueue_next`
+    start_at: Option<Duration>,      // Position the track being started begins at, for `play_file_from`
+}
+
+impl PlayerInner {
+    /// Sink volume: the user volume times the track's ReplayGain, when enabled
+    fn sink_volume(&self) -> f32 {
+        match self.track_gain.filter(|_| self.replay_gain) {
+            Some(gain) => self.volume * gain,
+            None => self.volume,
         }
     }
 }
 
-/// Return true if the current sink is paused, false otherwise.
-pub fn is_paused() -> bool {
-    let sink_guard = CURRENT_SINK.lock().expect("Failed to lock CURRENT_SINK");
-    sink_guard.as_ref().map(|s| s.is_paused()).unwrap_or(false)
+/// Flags read by the progress threads without taking the `PlayerInner` lock
+#[derive(Default)]
+struct PlayerFlags {
+    is_paused: AtomicBool,  // Last known pause state
+    stopped: AtomicBool,    // No track is loaded, or the current one has played out
+    generation: AtomicU64,  // Incremented on every play/stop so a replaced track doesn't report "finished"
+    seeked: AtomicBool,     // Set by `seek_to` so the progress thread reports where the seek landed
+    gapless: AtomicBool,    // `enqueue_next` may append the next track to the playing sink
 }
 
-/// Play the given MP3 file in a background thread, stopping any track already playing.
-/// Returns immediately so the caller (TUI) remains responsive.
-/// Errors are logged to stderr inside the spawned thread.
-pub fn play_file<P: AsRef<Path>>(path: P, progress_sender: Sender<(u64, u64)>) -> Result<(), String> {
-    let path_buf: PathBuf = path.as_ref().into();
+/// Handle to the audio player. Clones share the same state, so one can be moved
+/// into a background thread while the UI keeps another.
+#[derive(Clone)]
+pub struct Player {
+    inner: Arc<Mutex<PlayerInner>>,
+    flags: Arc<PlayerFlags>,
+}
 
-    thread::spawn(move || {
-        if let Err(e) = play_inner(&path_buf, progress_sender) {
-            eprintln!("[audio error] {e}");
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Why a player operation failed
+#[derive(Debug)]
+pub enum PlayerError {
+    FileOpen(io::Error),            // The track could not be opened
+    Decode(DecoderError),           // The track is not in a format the decoders understand
+    NoOutputDevice(StreamError),    // No audio output could be opened
+    SinkCreate(PlayError),          // The output opened but playback could not start on it
+    Seek(SeekError),                // The current track does not support seeking there
+    Unsupported(&'static str),      // A format left out of this build; the message says how to get it
+    NothingPlaying,                 // The operation needs a current track
+    GaplessOff,                     // `enqueue_next` was called with gapless playback off
+    Busy,                           // The audio thread held the sink for longer than LOCK_TIMEOUT
+    Connect(io::Error),             // A stream URL could not be opened
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerError::FileOpen(e) => write!(f, "Failed to open file: {e}"),
+            PlayerError::Decode(e) => write!(f, "Decode error: {e}"),
+            PlayerError::NoOutputDevice(e) => write!(f, "No output device: {e}"),
+            PlayerError::SinkCreate(e) => write!(f, "Sink error: {e}"),
+            PlayerError::Seek(e) => write!(f, "Seek failed: {e}"),
+            PlayerError::Unsupported(message) => f.write_str(message),
+            PlayerError::NothingPlaying => f.write_str("Nothing is playing"),
+            PlayerError::GaplessOff => f.write_str("Gapless playback is off"),
+            PlayerError::Busy => f.write_str("Audio thread is busy"),
+            PlayerError::Connect(e) => write!(f, "Failed to open stream: {e}"),
         }
-    });
+    }
+}
 
-    Ok(())
+impl Error for PlayerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlayerError::FileOpen(e) => Some(e),
+            PlayerError::Decode(e) => Some(e),
+            PlayerError::NoOutputDevice(e) => Some(e),
+            PlayerError::SinkCreate(e) => Some(e),
+            PlayerError::Seek(e) => Some(e),
+            PlayerError::Connect(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PlayerError {
+    fn from(e: io::Error) -> Self {
+        PlayerError::FileOpen(e)
+    }
 }
 
-fn play_inner(path: &Path, progress_sender: Sender<(u64, u64)>) -> Result<(), String> {
-    // Stop old sink if any, ensuring only one track plays at a time
-    if let Some(old_sink) = CURRENT_SINK.lock().expect("Failed to lock CURRENT_SINK").take() {
-        old_sink.stop();
+impl From<DecoderError> for PlayerError {
+    fn from(e: DecoderError) -> Self {
+        PlayerError::Decode(e)
     }
+}
 
-    let file = File::open(path).map_err(|e| format!("Failed to open {path:?}: {e}"))?;
-    let source = Decoder::new(BufReader::new(file)).map_err(|e| format!("Decode error: {e}"))?;
+impl From<StreamError> for PlayerError {
+    fn from(e: StreamError) -> Self {
+        PlayerError::NoOutputDevice(e)
+    }
+}
 
-    // Get total duration in seconds or 0 if unknown
-    let total_duration = source.total_duration().map(|d| d.as_secs()).unwrap_or(0);
+impl From<PlayError> for PlayerError {
+    fn from(e: PlayError) -> Self {
+        PlayerError::SinkCreate(e)
+    }
+}
 
-    let (_stream, handle) = OutputStream::try_default().map_err(|e| format!("No output device: {e}"))?;
-    let sink = Sink::try_new(&handle).map_err(|e| format!("Sink error: {e}"))?;
+impl From<SeekError> for PlayerError {
+    fn from(e: SeekError) -> Self {
+        PlayerError::Seek(e)
+    }
+}
 
-    let arc_sink = Arc::new(sink);
-    arc_sink.append(source);
+/// Messages from the playback threads to the UI, tagged with the generation of the
+/// playback they are about, so events of a replaced or stopped track can be dropped
+#[derive(Debug)]
+pub enum PlayerEvent {
+    Progress { generation: u64, elapsed: Duration, total: Duration }, // Sent every PROGRESS_INTERVAL while a track plays; total is zero if unknown
+    TrackEnded { generation: u64 },                       // The current track played to its end (or into a preloaded one)
+    SeekCompleted { generation: u64, position: Duration }, // A seek landed at this position
+    Error { generation: u64, error: PlayerError },        // Playback could not start
+    BpmDetected { generation: u64, bpm: f32 },            // Tempo estimated from the start of the current track
+}
 
-    // Save the Arc<Sink> so we can stop playback later if needed
-    *CURRENT_SINK.lock().expect("Failed to lock CURRENT_SINK") = Some(arc_sink.clone());
+impl PlayerEvent {
+    /// Generation of the playback that sent the event; see `Player::generation`
+    pub fn generation(&self) -> u64 {
+        match *self {
+            PlayerEvent::Progress { generation, .. }
+            | PlayerEvent::TrackEnded { generation }
+            | PlayerEvent::SeekCompleted { generation, .. }
+            | PlayerEvent::Error { generation, .. }
+            | PlayerEvent::BpmDetected { generation, .. } => generation,
+        }
+    }
+}
+
+/// What the player is doing, for display and for deciding when to move on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackState {
+    Stopped, // No track, or the last one played out
+    Playing,
+    Paused,
+}
 
-    // Track playback start time
-    let start = Instant::now();
+impl PlaybackState {
+    /// Glyph shown in the top bar
+    pub fn glyph(self) -> &'static str {
+        match self {
+            PlaybackState::Stopped => "⏹",
+            PlaybackState::Playing => "▶",
+            PlaybackState::Paused => "⏸",
+        }
+    }
+}
 
-    // Clone Arc<Sink> and Sender for the progress-reporting thread
-    let arc_sink_clone = arc_sink.clone();
-    let sender_clone = progress_sender.clone();
+/// File extensions of the formats the compiled-in rodio decoders can play
+pub fn supported_extensions() -> &'static [&'static str] {
+    if cfg!(feature = "aac") {
+        &["mp3", "flac", "ogg", "wav", "aac", "m4a"]
+    } else {
+        &["mp3", "flac", "ogg", "wav"]
+    }
+}
 
-    thread::spawn(move || {
-        let mut pause_duration = Duration::ZERO;
-        let mut last_check = Instant::now();
+/// Names of the output devices of the default audio host
+pub fn list_output_devices() -> Vec<String> {
+    cpal::default_host()
+        .output_devices()
+        .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Length of an audio file as reported by its decoder, if known.
+pub fn track_duration(path: &Path) -> Option<Duration> {
+    let file = File::open(path).ok()?;
+    Decoder::new(BufReader::new(file)).ok()?.total_duration()
+}
+
+/// Linear volume factor of a track's ReplayGain tag, if it has one
+fn replay_gain_factor(path: &Path) -> Option<f32> {
+    tagread::read_tags(path).replay_gain_db.map(|db| 10f32.powf(db / 20.0))
+}
+
+/// Sample rate (Hz) and channel count of an audio file, as decoded.
+pub fn stream_format(path: &Path) -> Option<(u32, u16)> {
+    let file = File::open(path).ok()?;
+    let decoder = Decoder::new(BufReader::new(file)).ok()?;
+    Some((decoder.sample_rate(), decoder.channels()))
+}
+
+impl Player {
+    /// A player with nothing playing, full volume and normal speed
+    pub fn new() -> Self {
+        let inner = PlayerInner { volume: 1.0, speed: 1.0, ..PlayerInner::default() };
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            flags: Arc::new(PlayerFlags { stopped: AtomicBool::new(true), ..PlayerFlags::default() }),
+        }
+    }
+
+    /// Lock the shared state, waiting as long as it takes. For the playback threads
+    /// and for settings that are only held for a moment. A poisoned lock is recovered.
+    fn lock(&self) -> MutexGuard<'_, PlayerInner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Lock the shared state, spinning for at most LOCK_TIMEOUT so the UI never hangs
+    /// on a stuck audio thread. A poisoned lock is recovered rather than propagated.
+    fn try_lock(&self) -> Result<MutexGuard<'_, PlayerInner>, PlayerError> {
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        loop {
+            match self.inner.try_lock() {
+                Ok(guard) => return Ok(guard),
+                Err(TryLockError::Poisoned(poisoned)) => return Ok(poisoned.into_inner()),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                Err(TryLockError::WouldBlock) => return Err(PlayerError::Busy),
+            }
+        }
+    }
+
+    /// Set the equalizer used for tracks started from now on.
+    /// Biquad filters can't be swapped into a playing source, so restart the track to hear it.
+    pub fn set_equalizer(&self, eq: Equalizer) {
+        self.lock().equalizer = eq;
+    }
+
+    /// Set the playback volume for the current and all following tracks.
+    pub fn set_volume(&self, volume: f32) {
+        let mut inner = self.lock();
+        inner.volume = volume;
+        if let Some(sink) = inner.current_sink.as_ref() {
+            sink.set_volume(inner.sink_volume());
+        }
+    }
+
+    /// Turn ReplayGain normalization on or off, for the current track as well
+    pub fn set_replay_gain(&self, enabled: bool) {
+        let mut inner = self.lock();
+        inner.replay_gain = enabled;
+        if let Some(sink) = inner.current_sink.as_ref() {
+            sink.set_volume(inner.sink_volume());
+        }
+    }
+
+    /// Whether the current track's volume is scaled by its ReplayGain
+    pub fn replay_gain_active(&self) -> bool {
+        self.try_lock().is_ok_and(|inner| inner.replay_gain && inner.track_gain.is_some())
+    }
+
+    /// Set the playback speed of the following tracks. The playing track keeps its speed
+    /// until it is restarted. Pitch changes with the speed.
+    pub fn set_speed(&self, speed: f32) {
+        self.lock().speed = speed;
+    }
+
+    /// Play the following tracks on the named output device instead of the system default.
+    pub fn set_preferred_device(&self, name: &str) {
+        self.lock().device_name = Some(name.to_string());
+    }
+
+    /// Open the preferred output device, or the system default when none is set or it is gone
+    fn open_output_stream(&self) -> Result<(OutputStream, OutputStreamHandle), PlayerError> {
+        let preferred = self.lock().device_name.clone();
+        let device = preferred.and_then(|name| {
+            cpal::default_host()
+                .output_devices()
+                .ok()?
+                .find(|device| device.name().is_ok_and(|n| n == name))
+        });
+
+        match device {
+            Some(device) => Ok(OutputStream::try_from_device(&device)?),
+            None => Ok(OutputStream::try_default()?),
+        }
+    }
+
+    /// Send the RMS levels of the following tracks to `tx`, about 20 per second.
+    pub fn set_level_sender(&self, tx: Sender<f32>) {
+        self.lock().level_sender = Some(tx);
+    }
+
+    /// Turn gapless playback on or off.
+    pub fn set_gapless(&self, enabled: bool) {
+        self.flags.gapless.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Decode a track and wrap it in the equalizer and the level meter
+    fn open_source(&self, path: &Path) -> Result<(MeteringSource<EqSource<impl Source<Item = f32>>>, Duration), PlayerError> {
+        let file = File::open(path)?;
+        Ok(self.wrap_decoder(Decoder::new(BufReader::new(file))?))
+    }
+
+    /// Wrap a decoder in the equalizer and the level meter
+    fn wrap_decoder<R: Read + Seek + Send + Sync + 'static>(&self, source: Decoder<R>) -> (MeteringSource<EqSource<impl Source<Item = f32>>>, Duration) {
+        // Total duration, or zero if unknown
+        let total_duration = source.total_duration().unwrap_or_default();
+
+        let inner = self.lock();
+        let source = EqSource::new(source.convert_samples::<f32>(), &inner.equalizer);
+        (MeteringSource::new(source, inner.level_sender.clone()), total_duration)
+    }
+
+    /// Append the next track to the playing sink so it starts without a gap when the
+    /// current one ends. The progress thread reports the end of the current track
+    /// (elapsed == total) at the boundary and then carries on with the new one.
+    pub fn enqueue_next<P: AsRef<Path>>(&self, path: P) -> Result<(), PlayerError> {
+        if !self.flags.gapless.load(Ordering::SeqCst) {
+            return Err(PlayerError::GaplessOff);
+        }
+        let path = path.as_ref();
+        check_supported(path)?;
+        let (source, total_duration) = self.open_source(path)?;
+        let gain = replay_gain_factor(path);
+
+        let mut inner = self.try_lock()?;
+        let sink = inner.current_sink.clone().ok_or(PlayerError::NothingPlaying)?;
+        inner.preloaded_total = Some(total_duration);
+        inner.preloaded_gain = gain;
+        sink.append(source);
+        Ok(())
+    }
+
+    /// Stop the current track, if any, without starting a new one. Its playback
+    /// thread won't report it as finished. Returns true if a track was stopped.
+    pub fn stop(&self) -> Result<bool, PlayerError> {
+        let mut inner = self.try_lock()?;
+        inner.track_gain = None;
+        let sink = inner.current_sink.take();
+        // Only now is the track really going away; a busy lock above leaves it playing
+        // and reporting as before. Bumped before the sink stops, so it isn't reported
+        // as finished.
+        self.next_generation();
+        if let Some(sink) = &sink {
+            sink.stop();
+        }
+        drop(inner);
+        self.flags.is_paused.store(false, Ordering::SeqCst);
+        self.flags.stopped.store(true, Ordering::SeqCst);
+        Ok(sink.is_some())
+    }
+
+    /// Jump to the given position of the current track.
+    pub fn seek_to(&self, pos: Duration) -> Result<(), PlayerError> {
+        match self.try_lock()?.current_sink.as_ref() {
+            // The sink counts positions in played time, which runs faster or slower with the speed
+            Some(sink) => sink
+                .try_seek(pos.div_f32(sink.speed()))
+                .map(|()| self.flags.seeked.store(true, Ordering::SeqCst))
+                .map_err(PlayerError::from),
+            None => Err(PlayerError::NothingPlaying),
+        }
+    }
+
+    /// Measure the output latency: the time between handing a short silent clip to a
+    /// fresh sink and the device starting to consume it. Returns `None` if there is
+    /// no output device or the clip never starts within two seconds.
+    /// Blocks for the duration of the measurement, so call it off the UI thread.
+    pub fn measure_latency(&self) -> Option<Duration> {
+        let (_stream, handle) = self.open_output_stream().ok()?;
+        let sink = Sink::try_new(&handle).ok()?;
+        sink.set_volume(0.0);
+
+        let silence = Zero::<f32>::new(2, 44_100).take_duration(Duration::from_millis(500));
+        let start = Instant::now();
+        sink.append(silence);
+
+        while sink.get_pos().is_zero() {
+            if start.elapsed() > Duration::from_secs(2) {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let latency = start.elapsed();
+        sink.stop();
+        Some(latency)
+    }
+
+    /// Toggle pause/resume of the current playing sink, if any.
+    /// Does nothing if the audio thread holds the lock for longer than LOCK_TIMEOUT.
+    pub fn toggle_pause(&self) {
+        if let Ok(inner) = self.try_lock() {
+            if let Some(sink) = inner.current_sink.as_ref() {
+                if sink.is_paused() {
+                    sink.play();
+                } else {
+                    sink.pause();
+                }
+                self.flags.is_paused.store(sink.is_paused(), Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// True if no track is loaded, or the current one has played out.
+    /// Doesn't wait for the audio thread, so it's cheap enough to call every frame.
+    pub fn is_stopped(&self) -> bool {
+        self.flags.stopped.load(Ordering::SeqCst)
+    }
 
-        while !arc_sink_clone.empty() {
-            let now = Instant::now();
+    /// True if a track is loaded, not paused and not played out
+    pub fn is_playing(&self) -> bool {
+        !self.is_paused() && !self.is_stopped()
+    }
+
+    /// Counter bumped whenever a track is started or stopped; events from older
+    /// generations are stale
+    pub fn generation(&self) -> u64 {
+        self.flags.generation.load(Ordering::SeqCst)
+    }
 
-            if arc_sink_clone.is_paused() {
-                pause_duration += now - last_check;
+    /// Start a new generation of playback, before the playback thread gets to it, so
+    /// the old track's events are stale right away
+    fn next_generation(&self) -> u64 {
+        self.flags.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Return true if the current sink is paused, false otherwise.
+    /// Reads the cached state, so it is safe to call from the render loop.
+    pub fn is_paused(&self) -> bool {
+        self.flags.is_paused.load(Ordering::SeqCst)
+    }
+
+    /// Play the given MP3 file in a background thread, stopping any track already playing.
+    /// Returns immediately so the caller (TUI) remains responsive.
+    /// Errors inside the spawned thread are sent as `PlayerEvent::Error`.
+    pub fn play_file<P: AsRef<Path>>(&self, path: P, progress_sender: Sender<PlayerEvent>) -> Result<(), PlayerError> {
+        self.play_file_with_fade(path, progress_sender, None)
+    }
+
+    /// Crossfade into the given file: the playing track fades out on its own sink while
+    /// the new one fades in over `duration_secs`, so both play for that long.
+    pub fn crossfade_to(&self, path: &Path, duration_secs: u32, progress_sender: Sender<PlayerEvent>) -> Result<(), PlayerError> {
+        let fade = Duration::from_secs(duration_secs.into());
+        self.play_file_with_fade(path, progress_sender, Some(fade))
+    }
+
+    /// Like `play_file`, but when `fade` is set the previous track fades out while the
+    /// new one fades in over that duration instead of being cut off.
+    pub fn play_file_with_fade<P: AsRef<Path>>(
+        &self,
+        path: P,
+        progress_sender: Sender<PlayerEvent>,
+        fade: Option<Duration>,
+    ) -> Result<(), PlayerError> {
+        self.spawn_play(path.as_ref(), progress_sender, fade, None)
+    }
+
+    /// Like `play_file`, but start `start` into the file, e.g. at a track of a cue sheet.
+    /// Positions reported and sought are still those in the whole file.
+    pub fn play_file_from(&self, path: &Path, start: Duration, progress_sender: Sender<PlayerEvent>) -> Result<(), PlayerError> {
+        self.spawn_play(path, progress_sender, None, Some(start))
+    }
+
+    fn spawn_play(
+        &self,
+        path: &Path,
+        progress_sender: Sender<PlayerEvent>,
+        fade: Option<Duration>,
+        start: Option<Duration>,
+    ) -> Result<(), PlayerError> {
+        let path_buf: PathBuf = path.into();
+        // Checked here rather than in the thread so the caller sees the error
+        check_supported(&path_buf)?;
+
+        let generation = self.next_generation();
+        let player = self.clone();
+        thread::spawn(move || {
+            let error_sender = progress_sender.clone();
+            if let Err(error) = player.play_inner(generation, &path_buf, progress_sender, fade, start) {
+                let _ = error_sender.send(PlayerEvent::Error { generation, error });
             }
-            last_check = now;
+        });
 
-            // Elapsed time minus time spent paused
-            let elapsed = start.elapsed().saturating_sub(pause_duration).as_secs();
+        Ok(())
+    }
 
-            let clamped_elapsed = if total_duration > 0 && elapsed > total_duration {
-                total_duration
-            } else {
-                elapsed
+    /// Play an internet radio stream or remote track from an `http://` or `https://` URL
+    /// in a background thread, like `play_file`. Streams report a total duration of zero
+    /// and can't seek.
+    pub fn play_url(&self, url: &str, progress_sender: Sender<PlayerEvent>) -> Result<(), PlayerError> {
+        let url = url.to_string();
+        let generation = self.next_generation();
+        let player = self.clone();
+        thread::spawn(move || {
+            let error_sender = progress_sender.clone();
+            if let Err(error) = player.play_url_inner(generation, &url, progress_sender) {
+                let _ = error_sender.send(PlayerEvent::Error { generation, error });
+            }
+        });
+
+        Ok(())
+    }
+
+    fn play_inner(
+        &self,
+        generation: u64,
+        path: &Path,
+        progress_sender: Sender<PlayerEvent>,
+        fade: Option<Duration>,
+        start: Option<Duration>,
+    ) -> Result<(), PlayerError> {
+        self.replace_current(fade)?;
+        self.detect_bpm(path, generation, progress_sender.clone());
+        let (mut source, total_duration) = self.open_source(path)?;
+        if let Some(start) = start {
+            // Seeking the source first keeps the start of the file from being heard
+            source.try_seek(start)?;
+            self.lock().start_at = Some(start);
+        }
+        let gain = replay_gain_factor(path);
+        self.play_source(generation, source, total_duration, gain, progress_sender, fade)
+    }
+
+    fn play_url_inner(&self, generation: u64, url: &str, progress_sender: Sender<PlayerEvent>) -> Result<(), PlayerError> {
+        self.replace_current(None)?;
+        let stream = HttpStream::open(url).map_err(PlayerError::Connect)?;
+        let (source, _) = self.wrap_decoder(Decoder::new(stream)?);
+        // Streams have no length, whatever the decoder estimates
+        self.play_source(generation, source, Duration::ZERO, None, progress_sender, None)
+    }
+
+    /// Estimate the tempo of a track in a background thread from its first
+    /// BPM_ANALYSIS_DURATION and report it, unless another track has started by then
+    fn detect_bpm(&self, path: &Path, generation: u64, progress_sender: Sender<PlayerEvent>) {
+        let path = path.to_path_buf();
+        let player = self.clone();
+        thread::spawn(move || {
+            let Some(decoder) = File::open(&path).ok().and_then(|file| Decoder::new(BufReader::new(file)).ok()) else {
+                return;
             };
+            let channels = decoder.channels().max(1) as usize;
+            let sample_rate = decoder.sample_rate();
+            let samples: Vec<f32> = decoder.convert_samples::<f32>().take_duration(BPM_ANALYSIS_DURATION).collect();
+            let mono: Vec<f32> = samples
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                .collect();
+
+            if let Some(bpm) = analysis::estimate_bpm(&mono, sample_rate) {
+                if player.flags.generation.load(Ordering::SeqCst) == generation {
+                    let _ = progress_sender.send(PlayerEvent::BpmDetected { generation, bpm });
+                }
+            }
+        });
+    }
 
-            let _ = sender_clone.send((clamped_elapsed, total_duration));
-            thread::sleep(Duration::from_millis(500));
+    /// Stop (or fade out) the current sink, if any, ensuring only one track is current
+    /// at a time. The new track's generation was started by the caller.
+    fn replace_current(&self, fade: Option<Duration>) -> Result<(), PlayerError> {
+        let old_sink = {
+            let mut inner = self.try_lock()?;
+            inner.preloaded_total = None;
+            inner.preloaded_gain = None;
+            inner.start_at = None;
+            inner.current_sink.take()
+        };
+        // Nothing is loaded until the new track's sink is in place
+        self.flags.stopped.store(true, Ordering::SeqCst);
+        if let Some(old_sink) = old_sink {
+            match fade {
+                Some(duration) => fade_out(old_sink, duration),
+                None => old_sink.stop(),
+            }
         }
-        // Send final update when playback finishes
-        let _ = sender_clone.send((total_duration, total_duration));
-    });
 
+        Ok(())
+    }
+
+    /// Play `source` on a new sink and report its progress until it ends or another
+    /// generation takes over. Blocks until the sink has played out.
+    fn play_source(
+        &self,
+        generation: u64,
+        source: impl Source<Item = f32> + Send + 'static,
+        total_duration: Duration,
+        gain: Option<f32>,
+        progress_sender: Sender<PlayerEvent>,
+        fade: Option<Duration>,
+    ) -> Result<(), PlayerError> {
+        let (_stream, handle) = self.open_output_stream()?;
+        let sink = Sink::try_new(&handle)?;
+
+        let arc_sink = Arc::new(sink);
+        {
+            let mut inner = self.try_lock()?;
+            inner.track_gain = gain;
+            arc_sink.set_volume(inner.sink_volume());
+            arc_sink.set_speed(inner.speed);
+            match fade {
+                Some(duration) => arc_sink.append(source.fade_in(duration)),
+                None => arc_sink.append(source),
+            }
+
+            // Save the Arc<Sink> so we can stop playback later if needed
+            inner.current_sink = Some(arc_sink.clone());
+        }
+        self.flags.is_paused.store(false, Ordering::SeqCst);
+        self.flags.stopped.store(false, Ordering::SeqCst);
+
+        // The sink counts from zero; seeking it as well makes it report positions in the file
+        let start_at = self.lock().start_at.take();
+        if let Some(start) = start_at {
+            arc_sink.try_seek(start.div_f32(arc_sink.speed()))?;
+        }
 
-    // Wait for playback to finish on the original Arc<Sink>
-    arc_sink.sleep_until_end();
+        // Clone Arc<Sink> and Sender for the progress-reporting thread
+        let arc_sink_clone = arc_sink.clone();
+        let sender_clone = progress_sender.clone();
+        let player = self.clone();
 
+        thread::spawn(move || {
+            let mut total_duration = total_duration;
+            let mut queued = arc_sink_clone.len();
+
+            while !arc_sink_clone.empty() {
+                // Stop reporting once another track has taken over
+                if player.flags.generation.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+
+                // A gapless track started: report the end of the previous one, then follow the new one
+                let len = arc_sink_clone.len();
+                if len < queued {
+                    let _ = sender_clone.send(PlayerEvent::TrackEnded { generation });
+                    let mut inner = player.lock();
+                    total_duration = inner.preloaded_total.take().unwrap_or_default();
+                    inner.track_gain = inner.preloaded_gain.take();
+                    arc_sink_clone.set_volume(inner.sink_volume());
+                }
+                queued = len;
+
+                // Position within the track; accounts for pauses, seeks and the playback speed
+                let elapsed = arc_sink_clone.get_pos().mul_f32(arc_sink_clone.speed());
+
+                let clamped_elapsed = if !total_duration.is_zero() && elapsed > total_duration {
+                    total_duration
+                } else {
+                    elapsed
+                };
+
+                if player.flags.seeked.swap(false, Ordering::SeqCst) {
+                    let _ = sender_clone.send(PlayerEvent::SeekCompleted { generation, position: clamped_elapsed });
+                }
+                let _ = sender_clone.send(PlayerEvent::Progress { generation, elapsed: clamped_elapsed, total: total_duration });
+                thread::sleep(PROGRESS_INTERVAL);
+            }
+            // Report the end of the track, unless another track replaced this one
+            if player.flags.generation.load(Ordering::SeqCst) == generation {
+                player.flags.stopped.store(true, Ordering::SeqCst);
+                let _ = sender_clone.send(PlayerEvent::TrackEnded { generation });
+            }
+        });
+
+
+        // Wait for playback to finish on the original Arc<Sink>
+        arc_sink.sleep_until_end();
+
+        Ok(())
+    }
+}
+
+/// Reject formats the built-in decoders can't handle, with a hint on how to get them
+fn check_supported(path: &Path) -> Result<(), PlayerError> {
+    if crate::is_known_unsupported(path) {
+        let is_opus = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("opus"));
+        return Err(PlayerError::Unsupported(if is_opus {
+            "Opus can't be played yet: rodio has no Opus decoder — see README"
+        } else {
+            "M4A/AAC requires building with `--features aac` — see README"
+        }));
+    }
     Ok(())
 }
 
+/// Lower the volume of a sink to zero over `duration` in a background thread, then stop it.
+fn fade_out(sink: Arc<Sink>, duration: Duration) {
+    const STEPS: u32 = 20;
+
+    thread::spawn(move || {
+        let start_volume = sink.volume();
+        for step in 1..=STEPS {
+            thread::sleep(duration / STEPS);
+            sink.set_volume(start_volume * (1.0 - step as f32 / STEPS as f32));
+        }
+        sink.stop();
+    });
+}
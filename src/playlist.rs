@@ -0,0 +1,38 @@
+// ============================================================================
+// em(π)trio MP3 Player — playlist.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Reads M3U/M3U8 playlists: one path per line, `#` lines are comments or
+// extended M3U directives. Relative paths are relative to the playlist file.
+// ============================================================================
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// True for `.m3u` and `.m3u8` files
+pub fn is_playlist_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("m3u") || ext.eq_ignore_ascii_case("m3u8"))
+        .unwrap_or(false)
+}
+
+/// Read the track paths listed in a playlist, in order.
+/// Paths are returned as listed; they are not checked for existence.
+pub fn parse_m3u(path: &Path) -> io::Result<Vec<PathBuf>> {
+    // Plain .m3u files are often Latin-1, so don't fail on invalid UTF-8
+    let bytes = fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let entries = text.lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base.join(line)) // `join` keeps absolute paths as they are
+        .collect();
+
+    Ok(entries)
+}
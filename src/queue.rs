@@ -0,0 +1,39 @@
+// ============================================================================
+// em(π)trio MP3 Player — queue.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// The play queue saved on exit and restored on the next start, as a JSON
+// array of paths at `<data dir>/empitrio/queue.json`.
+// ============================================================================
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+fn queue_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("empitrio").join("queue.json"))
+}
+
+/// The saved queue without tracks that no longer exist; empty if there is
+/// no saved queue or it can't be read.
+pub fn load() -> Vec<PathBuf> {
+    let Some(text) = queue_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    let paths: Vec<PathBuf> = serde_json::from_str(&text).unwrap_or_default();
+    paths.into_iter().filter(|path| path.is_file()).collect()
+}
+
+/// Save the queue, replacing the previous one
+pub fn save<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) -> io::Result<()> {
+    let path = queue_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory available"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let paths: Vec<&PathBuf> = paths.into_iter().collect();
+    let text = serde_json::to_string_pretty(&paths).map_err(io::Error::other)?;
+    fs::write(path, text)
+}
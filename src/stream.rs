@@ -0,0 +1,144 @@
+// ============================================================================
+// em(π)trio MP3 Player — stream.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Internet radio and remote tracks over HTTP and HTTPS: the response body of a
+// `reqwest` GET, wrapped as a `Read + Seek` reader that rodio's decoders can
+// play while it downloads.
+// ============================================================================
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use reqwest::blocking::{Client, Response};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A server silent for this long ends the stream instead of stalling playback forever
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Bytes kept from the start of the body, so the decoders can rewind while probing the format
+const REWIND_LIMIT: usize = 256 * 1024;
+
+/// True for text that should be played as a stream rather than a file
+pub fn is_stream_url(text: &str) -> bool {
+    text.starts_with("http://") || text.starts_with("https://")
+}
+
+/// The body of an HTTP response, read as it arrives. Seeking only works within
+/// the first REWIND_LIMIT bytes, or to the current end of what was received.
+pub struct HttpStream {
+    body: Response,
+    head: Vec<u8>, // Start of the body, for rewinds
+    pos: u64,      // Read position in the body
+    received: u64, // Bytes taken from the connection so far
+}
+
+impl HttpStream {
+    /// Send a GET request for `url`, following redirects, and return the body
+    /// of the first successful response
+    pub fn open(url: &str) -> io::Result<Self> {
+        let client = Client::builder()
+            .user_agent("empitrio")
+            .connect_timeout(CONNECT_TIMEOUT)
+            // Applies to each read of the body, so an endless stream isn't cut off
+            .timeout(READ_TIMEOUT)
+            .build()
+            .map_err(io::Error::other)?;
+        let body = client.get(url)
+            .send()
+            .and_then(Response::error_for_status)
+            .map_err(io::Error::other)?;
+        Ok(Self { body, head: Vec::new(), pos: 0, received: 0 })
+    }
+}
+
+impl Read for HttpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Replay the kept start of the body after a rewind
+        if self.pos < self.received {
+            if self.pos >= self.head.len() as u64 {
+                // Only the first REWIND_LIMIT bytes were kept; the rest is gone
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "Streams can't replay past their start"));
+            }
+            let n = (&self.head[self.pos as usize..]).read(buf)?;
+            self.pos += n as u64;
+            return Ok(n);
+        }
+
+        let n = self.body.read(buf)?;
+        let kept = n.min(REWIND_LIMIT - self.head.len());
+        self.head.extend_from_slice(&buf[..kept]);
+        self.pos += n as u64;
+        self.received += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for HttpStream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(_) => None,
+        };
+        match target {
+            Some(target) if target < self.head.len() as u64 || target == self.received => {
+                self.pos = target;
+                Ok(target)
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "Streams can't seek")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// URL of a one-shot local server answering with `body`
+    fn serve(body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request);
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+            socket.write_all(head.as_bytes()).unwrap();
+            socket.write_all(&body).unwrap();
+        });
+        format!("http://{addr}/track.mp3")
+    }
+
+    #[test]
+    fn rewinds_within_the_kept_start() {
+        let body: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        let mut stream = HttpStream::open(&serve(body.clone())).unwrap();
+        let mut first = [0; 100];
+        stream.read_exact(&mut first).unwrap();
+        stream.seek(SeekFrom::Start(10)).unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, body[10..]);
+    }
+
+    #[test]
+    fn replaying_past_the_kept_start_is_an_error() {
+        let body = vec![7u8; REWIND_LIMIT + 4096];
+        let mut stream = HttpStream::open(&serve(body)).unwrap();
+        let mut all = Vec::new();
+        stream.read_to_end(&mut all).unwrap();
+        stream.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut head = vec![0; REWIND_LIMIT];
+        stream.read_exact(&mut head).unwrap();
+        assert!(stream.read(&mut [0; 16]).is_err());
+    }
+}
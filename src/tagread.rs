@@ -0,0 +1,108 @@
+// ============================================================================
+// em(π)trio MP3 Player — tagread.rs
+// Author: Tom Papatolis
+// Email: tom@tpapatolis.com
+// Github: https://github.com/tomgineer/empitrio
+// ---------------------------------------------------------------------------
+// Description:
+// Reads ID3 tags (title, artist, album, genre, year, track, BPM, ReplayGain) from audio files so the
+// rest of the app can work with plain Rust values instead of raw ID3 frames.
+// ============================================================================
+
+use std::path::Path;
+
+use id3::frame::PictureType;
+use id3::{Tag, TagLike, Version};
+
+/// Tag values read from a single audio file. Missing frames are `None`.
+/// The technical details at the end are only filled in for the playing track.
+#[derive(Debug, Clone, Default)]
+pub struct TrackMeta {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,     // ID3v1 genre numbers are resolved to names
+    pub year: Option<i32>,
+    pub track: Option<u32>,
+    pub bpm: Option<f32>,
+    pub replay_gain_db: Option<f32>, // TXXX:REPLAYGAIN_TRACK_GAIN, e.g. -6.5 for "-6.50 dB"
+    pub bitrate_kbps: Option<u32>, // Constant or nominal bitrate
+    pub vbr: bool,                 // Variable bitrate MP3
+    pub sample_rate: Option<u32>,  // In Hz
+    pub channels: Option<u16>,
+    pub file_size: Option<u64>,    // In bytes
+}
+
+impl TrackMeta {
+    /// `Artist — Title`, or just the title when there is no artist.
+    /// `None` without a title, so callers can fall back to the file name.
+    pub fn display_title(&self) -> Option<String> {
+        let title = self.title.as_ref()?;
+        Some(match &self.artist {
+            Some(artist) => format!("{} — {}", artist, title),
+            None => title.clone(),
+        })
+    }
+}
+
+/// Read the ID3 tags of the given file.
+/// Files without a tag (or with an unreadable one) return empty `TrackMeta`.
+pub fn read_tags<P: AsRef<Path>>(path: P) -> TrackMeta {
+    match Tag::read_from_path(path) {
+        Ok(tag) => TrackMeta {
+            title: non_empty(tag.title()),
+            artist: non_empty(tag.artist()),
+            album: non_empty(tag.album()),
+            genre: non_empty(tag.genre_parsed().as_deref()),
+            year: tag.year().or_else(|| tag.date_recorded().map(|date| date.year)),
+            track: tag.track(),
+            bpm: tag.get("TBPM")
+                .and_then(|frame| frame.content().text())
+                .and_then(|text| text.trim().parse::<f32>().ok()),
+            replay_gain_db: tag.extended_texts()
+                .find(|text| text.description.eq_ignore_ascii_case("REPLAYGAIN_TRACK_GAIN"))
+                .and_then(|text| parse_gain(&text.value)),
+            ..TrackMeta::default()
+        },
+        Err(_) => TrackMeta::default(),
+    }
+}
+
+/// Embedded cover art (APIC frame) of the given file, preferring the front cover.
+pub fn read_cover_art<P: AsRef<Path>>(path: P) -> Option<Vec<u8>> {
+    let tag = Tag::read_from_path(path).ok()?;
+    let front = tag.pictures().find(|p| p.picture_type == PictureType::CoverFront);
+    front.or_else(|| tag.pictures().next()).map(|p| p.data.clone())
+}
+
+/// True for files whose tags are ID3: MP3s. FLAC and OGG keep theirs as Vorbis
+/// comments, which aren't read, and prepending an ID3 tag would corrupt them.
+pub fn has_id3_tags(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"))
+}
+
+/// Set the title frame of the given MP3 file, creating a tag if none exists.
+pub fn write_title<P: AsRef<Path>>(path: P, title: &str) -> Result<(), String> {
+    let path = path.as_ref();
+    if !has_id3_tags(path) {
+        return Err(format!("Not writing a title to {path:?}: only MP3 tags are supported"));
+    }
+    let mut tag = Tag::read_from_path(path).unwrap_or_else(|_| Tag::new());
+    tag.set_title(title);
+    tag.write_to_path(path, Version::Id3v24)
+        .map_err(|e| format!("Failed to write tag to {path:?}: {e}"))
+}
+
+/// A ReplayGain value like `-6.50 dB` in dB
+fn parse_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let number = value.strip_suffix("dB").or_else(|| value.strip_suffix("db")).unwrap_or(value);
+    number.trim().parse().ok()
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+}
@@ -6,34 +6,191 @@
 // ---------------------------------------------------------------------------
 // Description:
 // Defines color themes and styling used by the TUI for consistent look & feel.
+// The default theme's colors can be overridden from the `[theme]` table of the
+// config file, and over that from `EMPITRIO_COLOR_*` environment variables.
 // ============================================================================
 
 use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
 
+/// Built-in themes in the order `t` cycles through them
+pub const THEMES: [fn() -> Theme; 4] = [Theme::xcad, Theme::dracula, Theme::gruvbox, Theme::solarized_dark];
+
+/// Optional `[r, g, b]` overrides of the theme colors, read from `[theme]` in the config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub text: Option<[i64; 3]>,
+    #[serde(alias = "selection_text")] // Old name, still accepted
+    pub highlight_fg: Option<[i64; 3]>,
+    #[serde(alias = "selection_background")] // Old name, still accepted
+    pub highlight_bg: Option<[i64; 3]>,
+    pub title: Option<[i64; 3]>,
+    pub border: Option<[i64; 3]>,
+    pub block_text: Option<[i64; 3]>,
+    pub status_text: Option<[i64; 3]>,
+}
+
+/// Environment variables that override the theme colors, as `r,g,b`
+const COLOR_VARS: [&str; 7] = [
+    "EMPITRIO_COLOR_TEXT",
+    "EMPITRIO_COLOR_SELECTION_FG",
+    "EMPITRIO_COLOR_SELECTION_BG",
+    "EMPITRIO_COLOR_TITLE",
+    "EMPITRIO_COLOR_BORDER",
+    "EMPITRIO_COLOR_BLOCK_TEXT",
+    "EMPITRIO_COLOR_STATUS",
+];
+
+impl ThemeConfig {
+    /// Overrides from the `EMPITRIO_COLOR_*` variables (e.g. `EMPITRIO_COLOR_BORDER=150,150,150`).
+    /// Variables that are unset or not three comma-separated numbers are left out.
+    pub fn from_env() -> Self {
+        let [text, highlight_fg, highlight_bg, title, border, block_text, status_text] =
+            COLOR_VARS.map(|var| std::env::var(var).ok().and_then(|value| parse_rgb(&value)));
+        Self { text, highlight_fg, highlight_bg, title, border, block_text, status_text }
+    }
+
+    /// These overrides, with the ones set in `other` taking their place
+    pub fn overridden_by(&self, other: &ThemeConfig) -> Self {
+        Self {
+            text: other.text.or(self.text),
+            highlight_fg: other.highlight_fg.or(self.highlight_fg),
+            highlight_bg: other.highlight_bg.or(self.highlight_bg),
+            title: other.title.or(self.title),
+            border: other.border.or(self.border),
+            block_text: other.block_text.or(self.block_text),
+            status_text: other.status_text.or(self.status_text),
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, Option<[i64; 3]>); 7] {
+        [
+            ("text", self.text),
+            ("highlight_fg", self.highlight_fg),
+            ("highlight_bg", self.highlight_bg),
+            ("title", self.title),
+            ("border", self.border),
+            ("block_text", self.block_text),
+            ("status_text", self.status_text),
+        ]
+    }
+
+    /// True if any color is overridden
+    pub fn is_set(&self) -> bool {
+        self.fields().iter().any(|(_, rgb)| rgb.is_some())
+    }
+
+    /// Names of the colors with a component outside 0–255; those keep their default
+    pub fn invalid_fields(&self) -> Vec<&'static str> {
+        self.fields().into_iter()
+            .filter(|(_, rgb)| rgb.is_some() && to_color(*rgb).is_none())
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// `r,g,b` as `[r, g, b]`; the range is checked with the other overrides
+fn parse_rgb(value: &str) -> Option<[i64; 3]> {
+    let mut parts = value.split(',').map(|part| part.trim().parse().ok());
+    let rgb = [parts.next()??, parts.next()??, parts.next()??];
+    parts.next().is_none().then_some(rgb)
+}
+
+/// Color from an `[r, g, b]` override, if set and valid
+fn to_color(rgb: Option<[i64; 3]>) -> Option<Color> {
+    let [r, g, b] = rgb?;
+    Some(Color::Rgb(
+        u8::try_from(r).ok()?,
+        u8::try_from(g).ok()?,
+        u8::try_from(b).ok()?,
+    ))
+}
+
+/// Colors of the TUI; every color `ui.rs` draws with is one of these fields
 pub struct Theme {
-    pub text: Color,
-    pub selection_text: Color,
-    pub selection_background: Color,
-    pub title: Color,
-    pub border: Color,
-    pub block_text: Color,
-    pub status_text: Color,
+    pub name: &'static str,
+    pub text: Color,            // File names and panel contents
+    pub highlight_fg: Color,    // Text of the selected entry
+    pub highlight_bg: Color,    // Background of the selected entry, and the progress bar
+    pub title: Color,           // Border of the focused pane and the queue
+    pub border: Color,          // Borders of the other blocks
+    pub block_text: Color,      // Block titles, table headers and search matches
+    pub status_text: Color,     // Status bar
     //pub warning_text: Color,
 }
 
 impl Theme {
+    /// The xcad theme with the colors set in the config replacing its own
+    pub fn from_config(cfg: &ThemeConfig) -> Self {
+        let base = Self::xcad();
+        if !cfg.is_set() {
+            return base;
+        }
+        Self {
+            name: "custom",
+            text: to_color(cfg.text).unwrap_or(base.text),
+            highlight_fg: to_color(cfg.highlight_fg).unwrap_or(base.highlight_fg),
+            highlight_bg: to_color(cfg.highlight_bg).unwrap_or(base.highlight_bg),
+            title: to_color(cfg.title).unwrap_or(base.title),
+            border: to_color(cfg.border).unwrap_or(base.border),
+            block_text: to_color(cfg.block_text).unwrap_or(base.block_text),
+            status_text: to_color(cfg.status_text).unwrap_or(base.status_text),
+        }
+    }
+
     pub fn xcad() -> Self {
         Self {
+            name: "xcad",
             text: Color::Rgb(204, 204, 204),                 // #CCCCCC
-            selection_text: Color::Rgb(255, 255, 255),       // #FFFFFF
-            selection_background: Color::Rgb(43, 79, 255),   // #2B4FFF
-            title: Color::Rgb(92, 120, 255),                 // #2B4FFF
+            highlight_fg: Color::Rgb(255, 255, 255),         // #FFFFFF
+            highlight_bg: Color::Rgb(43, 79, 255),           // #2B4FFF
+            title: Color::Rgb(92, 120, 255),                 // #5C78FF
             border: Color::Rgb(150, 150, 150),               // #999999
-            block_text: Color::Rgb(255, 164, 69),            // #999999
+            block_text: Color::Rgb(255, 164, 69),            // #FFA445
             status_text: Color::Rgb(92, 120, 255),           // #5C78FF
             // warning_text: Color::Rgb(255, 64, 64)         // #FF4040
         }
     }
+
+    pub fn dracula() -> Self {
+        Self {
+            name: "dracula",
+            text: Color::Rgb(248, 248, 242),                 // #F8F8F2
+            highlight_fg: Color::Rgb(248, 248, 242),         // #F8F8F2
+            highlight_bg: Color::Rgb(68, 71, 90),            // #44475A
+            title: Color::Rgb(189, 147, 249),                // #BD93F9
+            border: Color::Rgb(98, 114, 164),                // #6272A4
+            block_text: Color::Rgb(255, 184, 108),           // #FFB86C
+            status_text: Color::Rgb(139, 233, 253),          // #8BE9FD
+        }
+    }
+
+    pub fn gruvbox() -> Self {
+        Self {
+            name: "gruvbox",
+            text: Color::Rgb(235, 219, 178),                 // #EBDBB2
+            highlight_fg: Color::Rgb(251, 241, 199),         // #FBF1C7
+            highlight_bg: Color::Rgb(69, 133, 136),          // #458588
+            title: Color::Rgb(250, 189, 47),                 // #FABD2F
+            border: Color::Rgb(146, 131, 116),               // #928374
+            block_text: Color::Rgb(254, 128, 25),            // #FE8019
+            status_text: Color::Rgb(131, 165, 152),          // #83A598
+        }
+    }
+
+    pub fn solarized_dark() -> Self {
+        Self {
+            name: "solarized_dark",
+            text: Color::Rgb(131, 148, 150),                 // #839496
+            highlight_fg: Color::Rgb(253, 246, 227),         // #FDF6E3
+            highlight_bg: Color::Rgb(38, 139, 210),          // #268BD2
+            title: Color::Rgb(38, 139, 210),                 // #268BD2
+            border: Color::Rgb(88, 110, 117),                // #586E75
+            block_text: Color::Rgb(203, 75, 22),             // #CB4B16
+            status_text: Color::Rgb(42, 161, 152),           // #2AA198
+        }
+    }
 }
 
 // Color theme "xcad" colors as RGB with hex comments:
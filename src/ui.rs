@@ -10,147 +10,1622 @@
 // ============================================================================
 
 use std::io;
-use std::time::{Instant, Duration};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
 
-use crossterm::event::{self, Event as CEvent, KeyCode, KeyEventKind};
+use crossterm::event::{self, Event as CEvent, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
 use ratatui::{
     backend::Backend,
-    layout::{Constraint, Direction, Layout},
-    widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph},
-    style::{Modifier, Style},
-    Terminal,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Sparkline, Table},
+    style::{Color, Modifier, Style},
+    Frame, Terminal,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::App;
-use crate::player;
+use crate::{dir_basename, is_playlist_file, is_track_entry, App, Pane, PlaylistField, SortBy};
+use crate::cue::is_cue_file;
+use crate::gauge::{TrackGauge, SPINNER};
+use crate::player::{self, PlaybackState};
+use crate::art;
+use crate::config::Action;
+use crate::history;
 use crate::theme::Theme;
 
-/// Main event/render loop
+/// Smallest terminal size the full layout is drawn at; below MIN_WIDTH nothing is
+/// drawn at all
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 8;
+
+/// Narrower terminals get the compact mini mode: list, progress line and status bar
+const MINI_MODE_WIDTH: u16 = 60;
+const MINI_MODE_MIN_HEIGHT: u16 = 3;
+
+/// Entries shown above and below the selection in mini mode
+const MINI_MODE_NEIGHBORS: usize = 2;
+
+/// Width of the album art panel, in cells; the art is square, so half as many rows
+const ART_WIDTH: u16 = 20;
+
+/// Two clicks on the same row within this time count as a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Seconds before the end of a track at which the next one is preloaded for gapless playback
+const GAPLESS_PRELOAD_SECS: u64 = 3;
+
+/// Keys that can't be remapped in the config, as listed in the help overlay (`?`)
+const FIXED_KEYS: &[(&str, &str)] = &[
+    ("PgUp/PgDn", "Page up/down"),
+    ("Home/End", "First/last entry"),
+    (":play all", "Play all in folder"),
+    (", / .", "Previous/next track"),
+    ("x", "Stop"),
+    ("< / >", "Speed down/up"),
+    ("[ / ]", "Set loop A/B"),
+    ("\\", "Clear A-B loop"),
+    ("s", "Shuffle"),
+    ("r", "Repeat mode"),
+    ("t", "Theme"),
+    ("Tab", "Queue panel"),
+    ("Shift+↑/↓", "Move upcoming track"),
+    ("T", "Folder tree"),
+    ("Shift+Tab", "Switch pane"),
+    ("i", "Track info"),
+    ("I", "File info"),
+    ("v", "Visualizer"),
+    ("a", "Album art"),
+    ("l", "Lyrics"),
+    ("u", "Play stream URL"),
+    ("c", "Go to playing track"),
+    ("V", "Select files"),
+    ("F2", "Rename file"),
+    ("Delete", "Delete file"),
+    ("H", "History"),
+    ("N", "Notifications"),
+    ("E", "Status bar lines"),
+    ("R", "Recursive listing"),
+    ("d", "Output device"),
+    ("G", "Smart playlist"),
+    ("b", "Bookmark entry"),
+    ("B", "Bookmarks"),
+    ("S", "Sleep timer"),
+    ("e", "Export playlist"),
+    ("m", "Mark listened"),
+    ("/", "Search (/~ for a regex)"),
+    (":", "Command"),
+    ("Ctrl+C", "Quit"),
+    ("Ctrl+G", "Jump to folder"),
+    ("o / Ctrl+O", "Sort order"),
+    ("Ctrl+B", "Bitrate column"),
+    ("Ctrl+H / Ctrl+.", "Hidden files"),
+    (":stats", "Stats"),
+    ("Ctrl+R", "Reset session stats"),
+    ("a-z, 0-9", "Jump to entry"),
+    ("?", "This help"),
+];
+
+/// Where the list and progress bar were last drawn, for mapping mouse clicks
+#[derive(Debug, Clone, Copy, Default)]
+struct HitAreas {
+    list: Rect,         // File list, including its borders
+    list_offset: usize, // Index of the first visible entry
+    gauge: Rect,        // Progress bar, including its borders
+}
+
+/// Main event/render loop. Returns when the user quits or `interrupted` is set.
 pub fn ui_loop<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
-    progress_tx: std::sync::mpsc::Sender<(u64, u64)>,
+    progress_tx: std::sync::mpsc::Sender<player::PlayerEvent>,
+    interrupted: &AtomicBool,
 ) -> io::Result<()> {
-    let theme = Theme::xcad();
-    let mut song_end_instant: Option<Instant> = None;
+    let mut crossfade_triggered = false;
+
+    let mut hit_areas = HitAreas::default();
+    let mut last_click: Option<(Instant, usize)> = None;
+    let mut was_notifying = false;
+
+    // Waiting for input also paces the redraws while nothing happens
+    let poll_timeout = Duration::from_millis(1000 / app.config.render_fps.clamp(1, 60) as u64);
 
     loop {
+        // SIGINT: quit like Ctrl+C, so the session is still saved
+        if interrupted.load(AtomicOrdering::Relaxed) {
+            break;
+        }
+
+        let theme = app.current_theme();
+
         // Update playback progress from the channel
         app.poll_progress();
+        app.poll_library_size();
+        app.poll_audio_latency();
+        app.poll_thumbnails();
+        app.poll_durations();
+        app.poll_levels();
+        app.poll_recursive_scan();
+        app.poll_tag_scan();
+        app.poll_dir();
+        #[cfg(feature = "mpris")]
+        app.poll_mpris(&progress_tx);
+        #[cfg(unix)]
+        app.poll_ipc(&progress_tx);
+        app.check_sleep_timer();
+        app.check_ab_loop();
 
-        // Auto-play next song when current song finishes
-        if app.total_time == 0 || app.current_time < app.total_time || player::is_paused() {
-            // Reset timer if song not finished or paused
-            song_end_instant = None;
-        } else {
-            match song_end_instant {
-                None => {
-                    song_end_instant = Some(Instant::now());
+        // Moving on early only makes sense while a track is actually playing
+        let playing = app.total_time > 0 && app.playback_state() == PlaybackState::Playing;
+
+        // Auto-play next song: crossfade early when enabled, otherwise once the track has ended
+        if app.crossfade_enabled && app.crossfade_secs > 0 {
+            let near_end = playing
+                && app.current_time + app.crossfade_secs * 1000 >= app.total_time;
+
+            if near_end && !crossfade_triggered {
+                crossfade_triggered = true;
+                app.crossfade_to_next(&progress_tx);
+            } else if !near_end {
+                crossfade_triggered = false;
+            }
+        }
+
+        // Gapless: hand the next track to the player shortly before this one ends
+        if app.gapless_enabled && !app.crossfade_enabled && playing
+            && app.current_time + GAPLESS_PRELOAD_SECS * 1000 >= app.total_time
+        {
+            app.preload_next();
+        }
+
+        if app.track_ended {
+            app.track_ended = false;
+            app.advance(&progress_tx);
+        }
+
+        // Skip the frame when nothing changed. Notifications and the sleep timer count
+        // down on screen, and an expired notification needs one more frame to disappear.
+        let notifying = app.current_notification().is_some();
+        let redraw = app.dirty || notifying || was_notifying || app.sleep_timer.is_some();
+        was_notifying = notifying;
+        if redraw {
+            terminal.draw(|f| {
+                let size = f.area();
+
+                // The layout breaks down on tiny terminals, so ask for more room instead
+                let min_height = if size.width < MINI_MODE_WIDTH { MINI_MODE_MIN_HEIGHT } else { MIN_HEIGHT };
+                if size.width < MIN_WIDTH || size.height < min_height {
+                    let message = "Terminal too small — please resize";
+                    let area = centered_rect(message.width() as u16, 1, size);
+                    let too_small = Paragraph::new(message)
+                        .style(Style::default().fg(theme.status_text))
+                        .alignment(Alignment::Center);
+                    f.render_widget(too_small, area);
+                    hit_areas = HitAreas::default();
+                    return;
+                }
+
+                hit_areas = if size.width < MINI_MODE_WIDTH {
+                    draw_mini_mode(f, app, &theme)
+                } else {
+                    draw_full_mode(f, app, &theme)
+                };
+            })?;
+            app.dirty = false;
+        }
+
+        if event::poll(poll_timeout)? {
+            let event = event::read()?;
+            app.dirty = true;
+            if let CEvent::Resize(_, _) = event {
+                // Redraw right away instead of on the next tick
+                terminal.autoresize()?;
+                continue;
+            }
+
+            if let CEvent::Mouse(mouse) = event {
+                let down = match mouse.kind {
+                    MouseEventKind::Down(button) => Some(button),
+                    _ => None,
+                };
+                let inside = |rect: Rect| {
+                    mouse.column > rect.x && mouse.column + 1 < rect.x + rect.width
+                        && mouse.row > rect.y && mouse.row + 1 < rect.y + rect.height
+                };
+
+                if let Some(button) = down.filter(|_| !app.show_help && app.dir_picker.is_none() && app.device_picker.is_none() && app.playlist_picker.is_none() && app.bookmark_picker.is_none() && app.rename_input.is_none() && app.url_input.is_none()) {
+                    // Entries of a previewed folder can't be clicked
+                    if inside(hit_areas.list) && app.tree_preview.is_none() {
+                        // Row inside the borders, shifted by the list's scroll offset
+                        let row = hit_areas.list_offset + (mouse.row - hit_areas.list.y - 1) as usize;
+                        if let Some(&index) = app.visible_indices().get(row) {
+                            app.selected = index;
+
+                            let double_click = last_click
+                                .is_some_and(|(at, clicked)| clicked == index && at.elapsed() < DOUBLE_CLICK);
+                            if button == MouseButton::Right || double_click {
+                                last_click = None;
+                                app.close_search();
+                                app.select(&progress_tx);
+                            } else {
+                                last_click = Some((Instant::now(), index));
+                            }
+                        }
+                    } else if inside(hit_areas.gauge) && button == MouseButton::Left {
+                        let inner_width = hit_areas.gauge.width.saturating_sub(2).max(1) as f64;
+                        let fraction = (mouse.column - hit_areas.gauge.x - 1) as f64 / inner_width;
+                        app.seek_to_fraction(fraction);
+                    }
                 }
-                Some(start) if start.elapsed() > Duration::from_millis(700) => {
-                    if app.next_mp3() {
-                        app.select(&progress_tx);
+            }
+
+            if let CEvent::Key(key_event) = event {
+                if key_event.kind == KeyEventKind::Press && app.show_help {
+                    // The help overlay swallows all keys until it is closed
+                    if matches!(key_event.code, KeyCode::Char('?') | KeyCode::Char('q') | KeyCode::Esc) {
+                        app.show_help = false;
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.pending_delete.is_some() {
+                    // Only `y` deletes; any other key keeps the file
+                    match key_event.code {
+                        KeyCode::Char('y') => app.confirm_delete_file(&progress_tx),
+                        _ => app.cancel_delete(),
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.dir_picker.is_some() {
+                    // Directory picker captures all keys until Enter or Esc
+                    let result_count = app.dir_picker.as_ref()
+                        .map(|picker| app.dir_picker_results(&picker.query).len())
+                        .unwrap_or(0);
+
+                    match key_event.code {
+                        KeyCode::Enter => app.fuzzy_dir_jump(),
+                        KeyCode::Esc => app.dir_picker = None,
+                        _ => {
+                            if let Some(picker) = app.dir_picker.as_mut() {
+                                match key_event.code {
+                                    KeyCode::Down => {
+                                        picker.selected = (picker.selected + 1).min(result_count.saturating_sub(1));
+                                    }
+                                    KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+                                    KeyCode::Backspace => {
+                                        picker.query.pop();
+                                        picker.selected = 0;
+                                    }
+                                    KeyCode::Char(c) => {
+                                        picker.query.push(c);
+                                        picker.selected = 0;
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.device_picker.is_some() {
+                    // Device picker captures all keys until Enter or Esc
+                    match key_event.code {
+                        KeyCode::Enter => app.select_output_device(&progress_tx),
+                        KeyCode::Esc => app.device_picker = None,
+                        _ => {
+                            if let Some(picker) = app.device_picker.as_mut() {
+                                match key_event.code {
+                                    KeyCode::Down | KeyCode::Char('j') => {
+                                        picker.selected = (picker.selected + 1).min(picker.devices.len() - 1);
+                                    }
+                                    KeyCode::Up | KeyCode::Char('k') => {
+                                        picker.selected = picker.selected.saturating_sub(1);
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.bookmark_picker.is_some() {
+                    // Bookmark list captures all keys until Enter, Esc or B
+                    match key_event.code {
+                        KeyCode::Enter => app.jump_to_bookmark(),
+                        KeyCode::Esc | KeyCode::Char('B') => app.bookmark_picker = None,
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            let last = app.bookmarks.len().saturating_sub(1);
+                            app.bookmark_picker = app.bookmark_picker.map(|i| (i + 1).min(last));
+                        }
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            app.bookmark_picker = app.bookmark_picker.map(|i| i.saturating_sub(1));
+                        }
+                        _ => {}
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.playlist_picker.is_some() {
+                    // Smart playlist picker captures all keys until a value is chosen or Esc
+                    match key_event.code {
+                        KeyCode::Enter => app.select_in_playlist_picker(),
+                        KeyCode::Esc => app.close_playlist_picker(),
+                        _ => {
+                            if let Some(picker) = app.playlist_picker.as_mut() {
+                                let count = match picker.field {
+                                    None => PlaylistField::ALL.len(),
+                                    Some(_) => picker.values.len(),
+                                };
+                                match key_event.code {
+                                    KeyCode::Down | KeyCode::Char('j') => {
+                                        picker.selected = (picker.selected + 1).min(count.saturating_sub(1));
+                                    }
+                                    KeyCode::Up | KeyCode::Char('k') => {
+                                        picker.selected = picker.selected.saturating_sub(1);
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.rename_input.is_some() {
+                    // The rename field captures all keys until Enter or Esc
+                    match key_event.code {
+                        KeyCode::Enter => app.confirm_rename(),
+                        KeyCode::Esc => app.cancel_rename(),
+                        KeyCode::Backspace => {
+                            if let Some(input) = app.rename_input.as_mut() {
+                                input.pop();
+                            }
+                        }
+                        KeyCode::Char(c) => {
+                            if let Some(input) = app.rename_input.as_mut() {
+                                input.push(c);
+                            }
+                        }
+                        _ => {}
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.url_input.is_some() {
+                    // The URL prompt captures all keys until Enter or Esc
+                    match key_event.code {
+                        KeyCode::Enter => app.confirm_url(&progress_tx),
+                        KeyCode::Esc => app.url_input = None,
+                        KeyCode::Backspace => {
+                            if let Some(input) = app.url_input.as_mut() {
+                                input.pop();
+                            }
+                        }
+                        KeyCode::Char(c) => {
+                            if let Some(input) = app.url_input.as_mut() {
+                                input.push(c);
+                            }
+                        }
+                        _ => {}
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.command.is_some() {
+                    // Command prompt captures all keys until Enter or Esc
+                    match key_event.code {
+                        KeyCode::Enter => app.run_command(&progress_tx),
+                        KeyCode::Esc => app.command = None,
+                        KeyCode::Backspace => {
+                            if let Some(command) = app.command.as_mut() {
+                                command.pop();
+                            }
+                        }
+                        KeyCode::Char(c) => {
+                            if let Some(command) = app.command.as_mut() {
+                                command.push(c);
+                            }
+                        }
+                        _ => {}
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.show_notifications {
+                    // The notification history stays open until Esc or N
+                    if matches!(key_event.code, KeyCode::Esc | KeyCode::Char('N')) {
+                        app.show_notifications = false;
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.show_history {
+                    // The history overlay stays open until Esc or H
+                    if matches!(key_event.code, KeyCode::Esc | KeyCode::Char('H')) {
+                        app.show_history = false;
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.search_query.is_some() {
+                    // Search captures typing; navigation keys move through the results
+                    match key_event.code {
+                        KeyCode::Enter => {
+                            let has_results = !app.filtered_indices.is_empty();
+                            app.close_search();
+                            if has_results {
+                                app.select(&progress_tx);
+                            }
+                        }
+                        KeyCode::Esc => app.close_search(),
+                        KeyCode::Down => app.next(),
+                        KeyCode::Up => app.previous(),
+                        KeyCode::PageDown => app.page_down(),
+                        KeyCode::PageUp => app.page_up(),
+                        KeyCode::Backspace => app.search_pop(),
+                        KeyCode::Char(c) => app.search_push(c),
+                        _ => {}
+                    }
+                } else if key_event.kind == KeyEventKind::Press && app.selection_mode {
+                    // Deleting the selection needs two Delete presses in a row
+                    if key_event.code != KeyCode::Delete {
+                        app.confirm_delete = false;
+                    }
+                    match key_event.code {
+                        KeyCode::Esc => {
+                            app.exit_selection_mode();
+                            app.set_status("Selection cleared".into());
+                        }
+                        KeyCode::Char(' ') => app.toggle_file_selection(),
+                        KeyCode::Down | KeyCode::Char('j') => app.extend_selection(true),
+                        KeyCode::Up | KeyCode::Char('k') => app.extend_selection(false),
+                        KeyCode::Enter => app.enqueue_selection(&progress_tx),
+                        KeyCode::Delete => app.delete_selection_with_confirm(&progress_tx),
+                        _ => {}
+                    }
+                } else if key_event.kind == KeyEventKind::Press {
+                    // Overwriting an exported playlist needs two `e` presses in a row
+                    if key_event.code != KeyCode::Char('e') {
+                        app.confirm_export_overwrite = false;
+                    }
+
+                    // Shift+↑/↓ reorder the upcoming tracks instead of moving the selection
+                    if key_event.modifiers.contains(KeyModifiers::SHIFT) && !app.tree_focused() {
+                        match key_event.code {
+                            KeyCode::Up => {
+                                app.move_up();
+                                continue;
+                            }
+                            KeyCode::Down => {
+                                app.move_down();
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    // Configurable bindings come first; Ctrl combinations are never remapped
+                    let action = if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                        None
+                    } else {
+                        app.config.action_for(key_event.code)
+                    };
+
+                    match action {
+                        Some(Action::Quit) => break,
+                        Some(Action::Pause) => app.pause(),
+                        Some(Action::Next) if app.tree_focused() => app.tree_next(),
+                        Some(Action::Previous) if app.tree_focused() => app.tree_previous(),
+                        Some(Action::Select) if app.tree_focused() => app.tree_select(),
+                        Some(Action::Next) => app.next(),
+                        Some(Action::Previous) => app.previous(),
+                        Some(Action::Select) => app.select(&progress_tx),
+                        Some(Action::SeekForward) => app.seek_forward(),
+                        Some(Action::SeekBackward) => app.seek_backward(),
+                        Some(Action::VolumeUp) => app.volume_up(),
+                        Some(Action::VolumeDown) => app.volume_down(),
+                        None => {}
+                    }
+                    if action.is_some() {
+                        continue;
+                    }
+
+                    match key_event.code {
+                        KeyCode::PageDown => app.page_down(),
+                        KeyCode::PageUp => app.page_up(),
+                        KeyCode::Home => app.select_first(),
+                        KeyCode::End => app.select_last(),
+                        KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => break,
+                        KeyCode::Char('r') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.reset_session_stats()
+                        }
+                        KeyCode::Char(':') => app.open_command(),
+                        KeyCode::Char('?') => app.show_help = true,
+                        KeyCode::Char('S') => app.toggle_sleep_timer(),
+                        KeyCode::Char('d') => app.open_device_picker(),
+                        KeyCode::Char('G') => app.open_playlist_picker(),
+                        KeyCode::Char('R') => app.toggle_recursive(),
+                        KeyCode::Char('[') => app.mark_loop_a(),
+                        KeyCode::Char(']') => app.mark_loop_b(),
+                        KeyCode::Char('\\') => app.clear_loop(),
+                        KeyCode::Char('/') => app.open_search(),
+                        KeyCode::Char('x') => {
+                            app.stop();
+                        }
+                        KeyCode::Char('m') => app.toggle_listened(),
+                        KeyCode::Char('s') => app.toggle_shuffle(),
+                        KeyCode::Char('r') => app.cycle_repeat(),
+                        KeyCode::Char('t') => app.cycle_theme(),
+                        KeyCode::Tab => app.show_queue = !app.show_queue,
+                        KeyCode::BackTab => app.switch_pane(),
+                        KeyCode::Char('T') => app.toggle_two_pane(),
+                        KeyCode::Char('H') => app.toggle_history(),
+                        KeyCode::Char('N') => app.toggle_notifications(),
+                        KeyCode::Char('E') => app.cycle_status_height(),
+                        KeyCode::Char('<') => app.speed_down(&progress_tx),
+                        KeyCode::Char('>') => app.speed_up(&progress_tx),
+                        KeyCode::Char('e') => app.export_playlist_with_confirm(),
+                        KeyCode::Char('g') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.open_dir_picker()
+                        }
+                        KeyCode::Char('b') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.toggle_bitrate_column()
+                        }
+                        KeyCode::Char('b') => app.toggle_bookmark(),
+                        KeyCode::Char('B') => app.open_bookmarks(),
+                        KeyCode::Char('.') | KeyCode::Char('h')
+                            if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+                        {
+                            app.toggle_hidden_files()
+                        }
+                        KeyCode::Char(',') => app.play_previous(&progress_tx),
+                        KeyCode::Char('.') => app.play_next(&progress_tx),
+                        KeyCode::Char('I') => app.toggle_file_info(),
+                        KeyCode::Char('i') => app.toggle_track_info(),
+                        KeyCode::Char('v') => app.toggle_visualizer(),
+                        KeyCode::Char('V') => app.enter_selection_mode(),
+                        KeyCode::F(2) => app.start_rename(),
+                        KeyCode::Delete => app.request_delete(),
+                        KeyCode::Char('a') => app.toggle_art(),
+                        KeyCode::Char('l') => app.toggle_lyrics(),
+                        KeyCode::Char('u') => app.open_url_input(),
+                        KeyCode::Char('c') => app.go_to_playing(),
+                        KeyCode::Char('o') => app.cycle_sort(),
+                        // Any other letter or digit jumps to the entries starting with it
+                        KeyCode::Char(c)
+                            if c.is_alphanumeric() && !key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+                        {
+                            app.jump_to_letter(c)
+                        }
+                        _ => {}
                     }
-                    song_end_instant = None;
                 }
-                _ => {}
             }
         }
+    }
 
-        terminal.draw(|f| {
-            let size = f.area();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(1), // TopBar
-                    Constraint::Min(2),    // File list
-                    Constraint::Length(2), // Help box (new)
-                    Constraint::Length(3), // Progress bar
-                    Constraint::Length(1), // Status bar
-                ].as_ref())
-                .split(size);
-
-            // Top Bar
-            let top_text = Paragraph::new(" e m p i t r i o — by @tomgineer {https://github.com/tomgineer/empitrio}")
-                .style(Style::default().fg(theme.title));
-            f.render_widget(top_text, chunks[0]);
-
-            // --- File list widget ---
-            let items: Vec<ListItem> = app.files.iter().map(|f| {
-                ListItem::new(f.as_str())
-                    .style(Style::default().fg(theme.text))
-            }).collect();
-
-            let list = List::new(items)
-                .block(
-                    Block::default()
-                        .title("┤   File List ├")
-                        .title_style(Style::default().fg(theme.block_text))
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(theme.border))
-                        .style(Style::default())
-                )
-                .highlight_symbol("▶ ")
-                .highlight_style(
-                    Style::default()
-                        .fg(theme.selection_text)
-                        .bg(theme.selection_background)
-                        .add_modifier(Modifier::BOLD),
-                );
+    Ok(())
+}
+
+/// The normal layout: top bar, file list with its side panels, help, progress bar,
+/// visualizer, status bar and the overlays
+fn draw_full_mode(f: &mut Frame, app: &mut App, theme: &Theme) -> HitAreas {
+    let size = f.area();
+    let mut hit_areas = HitAreas::default();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1), // TopBar
+            Constraint::Min(2),    // File list
+            Constraint::Length(2), // Help box (new)
+            Constraint::Length(3), // Progress bar
+            Constraint::Length(if app.show_visualizer { 4 } else { 0 }), // Visualizer
+            Constraint::Length(app.status_height), // Status bar, one line per message shown
+        ].as_ref())
+        .split(size);
 
-            let mut state = ListState::default();
-            state.select(Some(app.selected));
-            f.render_stateful_widget(list, chunks[1], &mut state);
+    // Top Bar
+    let top_text = Paragraph::new(format!(
+        " {} e m p i t r i o — by @tomgineer {{https://github.com/tomgineer/empitrio}} | {}",
+        app.playback_state().glyph(),
+        app.session_summary()
+    ))
+        .style(Style::default().fg(theme.title));
+    f.render_widget(top_text, chunks[0]);
 
-            // --- Help Box ---
-            let help_text = Paragraph::new("Help: q - Quit | p/Space - Pause/Play | ↑/↓ or j/k - Navigate | Enter - Play")
-                .style(Style::default().fg(theme.text));
-            f.render_widget(help_text, chunks[2]);
+    let theme_name = Paragraph::new(format!("{} ", theme.name))
+        .style(Style::default().fg(theme.border))
+        .alignment(Alignment::Right);
+    f.render_widget(theme_name, chunks[0]);
 
-            // --- Progress bar ---
-            let progress_label = if app.total_time == 0 {
-                // Unknown duration
-                format!("┤  Progress: --:-- / --:-- ├")
+    // --- Queue panel on the right, when open ---
+    let (browser_area, queue_area) = if app.show_queue {
+        let parts = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .split(chunks[1]);
+        (parts[0], Some(parts[1]))
+    } else {
+        (chunks[1], None)
+    };
+
+    // --- Album art panel on the right of the list, when open and there is art ---
+    let cover = if app.show_art { app.load_current_art().cloned() } else { None };
+    let browser_area = match cover {
+        Some(cover) => {
+            let parts = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(20), Constraint::Length(ART_WIDTH + 2)])
+                .split(browser_area);
+            let block = Block::default()
+                .title("┤ Cover ├")
+                .title_style(Style::default().fg(theme.block_text))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.border));
+            let inner = block.inner(parts[1]);
+            let height = inner.height.min(ART_WIDTH / 2);
+            f.render_widget(Paragraph::new(art::render_art(&cover, inner.width, height)).block(block), parts[1]);
+            parts[0]
+        }
+        None => browser_area,
+    };
+
+    // --- Thumbnail of the selected track's cover, when enabled in the config ---
+    let browser_area = match app.files.get(app.selected).filter(|f| app.config.show_album_art && is_track_entry(f)) {
+        Some(name) => {
+            let parts = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(20), Constraint::Length(art::THUMBNAIL_COLS + 2)])
+                .split(browser_area);
+            let lines = match app.thumbnail_cache.get(name) {
+                Some(pixels) => art::render_thumbnail(pixels),
+                // The spinner advances as thumbnails arrive
+                None if app.thumbnails_loading() => {
+                    vec![Line::from(SPINNER[app.thumbnail_cache.len() % SPINNER.len()])]
+                }
+                None => vec![Line::from("no art")],
+            };
+            let block = Block::default()
+                .title("┤ Art ├")
+                .title_style(Style::default().fg(theme.block_text))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.border));
+            let thumbnail = Paragraph::new(lines)
+                .style(Style::default().fg(theme.text))
+                .alignment(Alignment::Center)
+                .block(block);
+            f.render_widget(thumbnail, parts[1]);
+            parts[0]
+        }
+        None => browser_area,
+    };
+
+    // --- Lyrics panel on the right of the list, when open ---
+    let browser_area = if app.show_lyrics {
+        let parts = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(browser_area);
+        draw_lyrics(f, app, theme, parts[1]);
+        parts[0]
+    } else {
+        browser_area
+    };
+
+    if let Some(area) = queue_area {
+        let mut items: Vec<ListItem> = Vec::new();
+        if let Some(playing) = &app.playing {
+            let name = playing.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            items.push(ListItem::new(name).style(
+                Style::default()
+                    .fg(theme.highlight_fg)
+                    .bg(theme.highlight_bg)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        items.extend(app.upcoming_tracks().into_iter()
+            .map(|name| ListItem::new(name).style(Style::default().fg(theme.text))));
+
+        let queue = List::new(items)
+            .block(
+                Block::default()
+                    .title("┤   Up Next ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.title))
+            );
+        f.render_widget(queue, area);
+    }
+
+    // --- Directory tree pane on the left of the file list (`T`) ---
+    let browser_area = if app.two_pane {
+        let parts = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+            .split(browser_area);
+
+        let items: Vec<ListItem> = app.dir_tree.iter().map(|(dir, depth)| {
+            let style = if *dir == app.current_dir {
+                Style::default().fg(theme.block_text).add_modifier(Modifier::BOLD)
             } else {
-                let current_time = format!("{:02}:{:02}", app.current_time / 60, app.current_time % 60);
-                let total_time = format!("{:02}:{:02}", app.total_time / 60, app.total_time % 60);
-                format!("┤  Progress: {} / {} ├", current_time, total_time)
+                Style::default().fg(theme.text)
             };
+            ListItem::new(format!("{}{}", "  ".repeat(*depth), dir_basename(dir))).style(style)
+        }).collect();
+        let border = if app.pane_focus == Pane::Left { theme.title } else { theme.border };
+        let tree = List::new(items)
+            .block(
+                Block::default()
+                    .title("┤ Folders ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(border))
+            )
+            .highlight_symbol("▶ ")
+            .highlight_style(
+                Style::default()
+                    .fg(theme.highlight_fg)
+                    .bg(theme.highlight_bg)
+                    .add_modifier(Modifier::BOLD),
+            );
+        let mut state = ListState::default();
+        state.select(Some(app.dir_tree_selected));
+        f.render_stateful_widget(tree, parts[0], &mut state);
+        parts[1]
+    } else {
+        browser_area
+    };
+
+    // --- File list widget (shares its area with the file info panel when open) ---
+    let (list_area, info_area) = if app.show_file_info {
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(2), Constraint::Length(3)])
+            .split(browser_area);
+        (parts[0], Some(parts[1]))
+    } else {
+        (browser_area, None)
+    };
+
+    // --- Track info panel: technical details of the playing track ---
+    let track_meta = if app.show_track_info { app.load_current_meta().cloned() } else { None };
+    let list_area = match &track_meta {
+        Some(meta) => {
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(2), Constraint::Length(8)])
+                .split(list_area);
 
-            let gauge = Gauge::default()
+            let unknown = || "-".to_string();
+            let bitrate = match (meta.bitrate_kbps, meta.vbr) {
+                (_, true) => "VBR".to_string(),
+                (Some(kbps), false) => format!("{} kbps", kbps),
+                (None, false) => unknown(),
+            };
+            let channels = meta.channels.map_or_else(unknown, |channels| match channels {
+                1 => "1 (mono)".to_string(),
+                2 => "2 (stereo)".to_string(),
+                n => n.to_string(),
+            });
+            let rows = vec![
+                Row::new(vec!["Bitrate".to_string(), bitrate]),
+                Row::new(vec!["Sample rate".to_string(), meta.sample_rate.map_or_else(unknown, |hz| format!("{} Hz", hz))]),
+                Row::new(vec!["Channels".to_string(), channels]),
+                Row::new(vec![
+                    "File size".to_string(),
+                    meta.file_size.map_or_else(unknown, |bytes| format!("{:.1} MB", bytes as f64 / 1_048_576.0)),
+                ]),
+                Row::new(vec!["Album".to_string(), meta.album.clone().unwrap_or_else(unknown)]),
+                Row::new(vec!["BPM".to_string(), app.current_bpm.map_or_else(unknown, |bpm| format!("{:.0}", bpm))]),
+            ];
+            let table = Table::new(rows, [Constraint::Length(12), Constraint::Min(10)])
+                .style(Style::default().fg(theme.text))
                 .block(
                     Block::default()
-                        .title(progress_label)
+                        .title("┤ Track Info ├")
                         .title_style(Style::default().fg(theme.block_text))
                         .borders(Borders::ALL)
                         .border_style(Style::default().fg(theme.border))
-                )
-                .gauge_style(Style::default().fg(theme.selection_background))
-                .ratio(app.perc_played as f64 / 100.0);
+                );
+            f.render_widget(table, parts[1]);
+            parts[0]
+        }
+        None => list_area,
+    };
 
-            f.render_widget(gauge, chunks[3]);
+    // Room for the name and the BPM column inside the borders and highlight symbol
+    let list_width = list_area.width.saturating_sub(4) as usize;
 
-            // --- Status bar ---
-            let status = Paragraph::new(app.status.as_str())
-                .style(Style::default().fg(theme.status_text));
-            f.render_widget(status, chunks[4]);
-        })?;
+    // Bitrate badges only fit on reasonably wide terminals
+    let show_badges = app.display_bitrate_in_list && size.width >= 60;
 
-        if event::poll(Duration::from_millis(250))? {
-            if let CEvent::Key(key_event) = event::read()? {
-                if key_event.kind == KeyEventKind::Press {
-                    match key_event.code {
-                        KeyCode::Char('q') | KeyCode::Esc => break,
-                        KeyCode::Char('p') | KeyCode::Char(' ') => app.pause(),
-                        KeyCode::Down | KeyCode::Char('j') => app.next(),
-                        KeyCode::Up | KeyCode::Char('k') => app.previous(),
-                        KeyCode::Enter => app.select(&progress_tx),
-                        _ => {}
+    // While searching only the matching entries are listed
+    let visible = app.visible_indices();
+    let playing_index = app.playing_index();
+
+    let items: Vec<ListItem> = visible.iter().map(|&i| {
+        let f = &app.files[i];
+        // The playing file of a cue sheet is named after the track playing in it
+        let name = match app.current_cue_track().filter(|track| track.file == app.current_dir.join(f)) {
+            Some(track) => format!("♪ {}", track.title),
+            None => f.clone(),
+        };
+        let mut label = if app.listened.contains(f) {
+            format!("{} ✓", name)
+        } else {
+            name
+        };
+        if app.read_only.contains(f) {
+            label = format!("{} 🔒", label);
+        }
+        if is_playlist_file(Path::new(f)) {
+            label = format!("{} [PL]", label);
+        }
+        if is_cue_file(Path::new(f)) {
+            label = format!("{} [CUE]", label);
+        }
+        if app.is_bookmarked(f) {
+            label = format!("{} ★", label);
+        }
+
+        // Right-aligned columns: BPM (in BPM sort mode), the bitrate badge and the duration
+        let mut columns: Vec<Span> = Vec::new();
+        let is_file = is_track_entry(f);
+
+        if app.sort_mode == SortBy::Bpm && is_file {
+            let bpm = app.meta_cache.get(&app.current_dir.join(f))
+                .and_then(|meta| meta.bpm)
+                .map(|bpm| format!("{:.1}", bpm))
+                .unwrap_or_default();
+            columns.push(Span::raw(format!(" {:>6}", bpm)));
+        }
+
+        if show_badges && is_file {
+            if let Some(badge) = app.bitrate_badge(f) {
+                // Unsupported formats stand out instead of being dimmed
+                let style = if badge.ends_with("?]") {
+                    Style::default().fg(theme.block_text)
+                } else {
+                    Style::default().add_modifier(Modifier::DIM)
+                };
+                columns.push(Span::styled(format!(" {:>7}", badge), style));
+            }
+        }
+
+        if is_file {
+            let duration = app.durations.get(f)
+                .map(|&secs| App::format_duration(secs))
+                .unwrap_or_default();
+            columns.push(Span::raw(format!(" {:>5}", duration)));
+        }
+
+        // Upcoming tracks are marked, as Shift+↑/↓ can reorder them
+        let queued = is_file && playing_index.is_some_and(|playing| i > playing);
+        let columns_width: usize = columns.iter().map(|span| span.width()).sum();
+        if columns_width > 0 {
+            // The selection and upcoming-track marks take two more columns
+            let mark_width = if app.selection_mode || queued { 2 } else { 0 };
+            label = fit_width(&label, list_width.saturating_sub(columns_width + mark_width));
+        }
+
+        // Highlight the search match; the label always starts with the file name,
+        // but may have been cut short
+        let matched = app.search_match(f)
+            .filter(|range| !range.is_empty())
+            .filter(|range| label.is_char_boundary(range.start) && label.is_char_boundary(range.end));
+        let mut spans = match matched {
+            Some(range) => vec![
+                Span::raw(label[..range.start].to_string()),
+                Span::styled(
+                    label[range.clone()].to_string(),
+                    Style::default().fg(theme.block_text).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(label[range.end..].to_string()),
+            ],
+            None => vec![Span::raw(label)],
+        };
+        spans.extend(columns);
+        // The highlighted entry becomes an edit field while renaming
+        if let Some(input) = app.rename_input.as_ref().filter(|_| i == app.selected) {
+            let ext = Path::new(f).extension()
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+                .unwrap_or_default();
+            spans = vec![
+                Span::raw(input.clone()),
+                Span::styled("█", Style::default().fg(theme.block_text)),
+                Span::raw(ext),
+            ];
+        }
+        if app.selection_mode {
+            let mark = if app.selected_files.contains(f) { "✓ " } else { "  " };
+            spans.insert(0, Span::styled(
+                mark,
+                Style::default().fg(theme.block_text).add_modifier(Modifier::BOLD),
+            ));
+        } else if queued {
+            spans.insert(0, Span::styled("› ", Style::default().fg(theme.border)));
+        }
+        ListItem::new(Line::from(spans))
+            .style(Style::default().fg(theme.text))
+    }).collect();
+
+    // Breadcrumb title: the current directory, shortened from the left to fit
+    let hidden_marker = if app.show_hidden_files { " [+hidden]" } else { "" };
+    let sort_marker = format!(" [{}]", app.sort_mode.indicator());
+    let title_room = (list_area.width as usize)
+        .saturating_sub(10 + hidden_marker.width() + sort_marker.width()); // corners, "┤ ", icon, " ├"
+    let list_title = format!(
+        "┤   {}{}{} ├",
+        truncate_path(&app.current_dir, title_room),
+        hidden_marker,
+        sort_marker
+    );
+
+    // A folder previewed from the tree pane replaces the listing until it is entered
+    let (items, list_title) = match &app.tree_preview {
+        Some((dir, entries)) => (
+            entries.iter()
+                .map(|name| ListItem::new(name.clone()).style(Style::default().fg(theme.text)))
+                .collect(),
+            format!("┤   {} [preview] ├", truncate_path(dir, title_room.saturating_sub(10))),
+        ),
+        None => (items, list_title),
+    };
+    let border = if app.two_pane && app.pane_focus == Pane::Right { theme.title } else { theme.border };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(list_title)
+                .title_style(Style::default().fg(theme.block_text))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(border))
+                .style(Style::default())
+        )
+        .highlight_symbol("▶ ")
+        .highlight_style(
+            Style::default()
+                .fg(theme.highlight_fg)
+                .bg(theme.highlight_bg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    // Rows inside the borders, for paging
+    app.list_height = list_area.height.saturating_sub(2);
+
+    let mut state = ListState::default();
+    if app.tree_preview.is_none() {
+        state.select(visible.iter().position(|&i| i == app.selected));
+    }
+    f.render_stateful_widget(list, list_area, &mut state);
+    hit_areas.list = list_area;
+    hit_areas.list_offset = state.offset();
+
+    // --- File info panel ---
+    if let Some(area) = info_area {
+        let info = Paragraph::new(app.show_file_permissions().unwrap_or_default())
+            .style(Style::default().fg(theme.text))
+            .block(
+                Block::default()
+                    .title("┤   File Info ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            );
+        f.render_widget(info, area);
+    }
+
+    // --- Help Box ---
+    let help_text = Paragraph::new("Help: q - Quit | p/Space - Pause/Play | x - Stop | ↑/↓ or j/k - Navigate | Enter - Play | ←/→ - Seek | ,/. - Prev/Next | +/- - Volume | </> - Speed | [/] - Loop A/B | s - Shuffle | r - Repeat | t - Theme | Tab - Queue | i - Track Info | v - Visualizer | a - Album Art | l - Lyrics | u - Stream URL | c - Go to Playing | o - Sort | T - Tree | Shift+Tab - Switch Pane | V - Select | F2 - Rename | Del - Delete | H - History | N - Notifications | E - Status Lines | R - Recursive | d - Output Device | G - Smart Playlist | b/B - Bookmark/Bookmarks | S - Sleep Timer | e - Export Playlist | m - Mark Listened | / - Search | : - Command | ? - All Keys")
+        .style(Style::default().fg(theme.text));
+    f.render_widget(help_text, chunks[2]);
+
+    // --- Progress bar ---
+    let streaming = app.is_streaming();
+    let progress_label = if streaming {
+        format!("┤  Progress: {} / live ├", App::format_position(app.current_time))
+    } else if app.total_time == 0 {
+        // Unknown duration
+        "┤  Progress: --:-- / --:-- (--:--) ├".to_string()
+    } else {
+        let current_time = App::format_position(app.current_time);
+        let total_time = App::format_position(app.total_time);
+        // Zero left is a real time here, not "unknown"
+        let remaining = match app.remaining_secs() {
+            Some(0) => "-00:00".to_string(),
+            Some(secs) => format!("-{}", App::format_duration(secs)),
+            None => "--:--".to_string(),
+        };
+        format!("┤  Progress: {} / {} ({}) ├", current_time, total_time, remaining)
+    };
+
+    // The A-B loop goes next to the times, e.g. `[A→B 01:05.0-01:40.5]`
+    let progress_label = match app.ab_loop() {
+        Some((a, b)) => format!(
+            "{}[A→B {}-{}] ├",
+            progress_label.trim_end_matches('├'),
+            App::format_position(a),
+            App::format_position(b)
+        ),
+        None => progress_label,
+    };
+
+    // Tick marks at the A-B loop points
+    let marks: Vec<(u64, Color)> = [app.loop_a, app.loop_b].into_iter()
+        .flatten()
+        .map(|position| (position, theme.block_text))
+        .collect();
+    let gauge = TrackGauge::new(app.current_time, app.total_time, &marks)
+        .block(
+            Block::default()
+                .title(progress_label)
+                .title_style(Style::default().fg(theme.block_text))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.border))
+        )
+        .label_style(Style::default().fg(theme.text));
+
+    f.render_widget(gauge, chunks[3]);
+    hit_areas.gauge = chunks[3];
+
+    // --- Visualizer: the most recent levels, newest on the right ---
+    if app.show_visualizer {
+        let width = chunks[4].width as usize;
+        let bars: Vec<u64> = app.levels.iter()
+            .skip(app.levels.len().saturating_sub(width))
+            .map(|level| (level.clamp(0.0, 1.0) * 100.0) as u64)
+            .collect();
+        let sparkline = Sparkline::default()
+            .data(&bars)
+            .max(50)
+            .style(Style::default().fg(theme.highlight_bg));
+        f.render_widget(sparkline, chunks[4]);
+    }
+
+    // --- Status bar: older messages above the current one when expanded ---
+    let older = app.status_height.saturating_sub(1) as usize;
+    let mut status_lines: Vec<Line> = app.previous_statuses(older)
+        .map(|message| Line::styled(message.to_string(), Style::default().fg(theme.border)))
+        .collect();
+    status_lines.push(Line::styled(status_text(app), status_style(app, theme)));
+    // The current message stays on the bottom line
+    while status_lines.len() < app.status_height as usize {
+        status_lines.insert(0, Line::default());
+    }
+    let status = Paragraph::new(status_lines)
+        .style(Style::default().fg(theme.status_text));
+    let status_area = chunks[5];
+    f.render_widget(status, status_area);
+
+    let shuffle = if app.shuffle { "[SHUFFLE] " } else { "" };
+    let replay_gain = if app.player.replay_gain_active() { "[RG] " } else { "" };
+    let speed = if app.speed != 1.0 { format!("{:.1}× ", app.speed) } else { String::new() };
+    let sleep = app.sleep_remaining()
+        .map(|left| format!("Sleep: {}m{:02}s ", left.as_secs() / 60, left.as_secs() % 60))
+        .unwrap_or_default();
+    let indicators = Paragraph::new(format!(
+        "{}{}{}{}{}Vol: {:.0}% ",
+        sleep, app.repeat.indicator(), shuffle, replay_gain, speed, app.volume * 100.0
+    ))
+        .style(Style::default().fg(theme.status_text))
+        .alignment(Alignment::Right);
+    let last_line = Rect { y: status_area.bottom().saturating_sub(1), height: status_area.height.min(1), ..status_area };
+    f.render_widget(indicators, last_line);
+
+    // --- Directory picker overlay ---
+    if let Some(picker) = &app.dir_picker {
+        let results = app.dir_picker_results(&picker.query);
+        let highlight = Style::default().fg(theme.block_text).add_modifier(Modifier::BOLD);
+
+        let items: Vec<ListItem> = results.iter().map(|(dir, found)| {
+            let mut spans: Vec<Span> = dir_basename(dir).chars().enumerate()
+                .map(|(i, c)| {
+                    if found.positions.contains(&i) {
+                        Span::styled(c.to_string(), highlight)
+                    } else {
+                        Span::raw(c.to_string())
                     }
+                })
+                .collect();
+            if let Some(parent) = dir.parent() {
+                spans.push(Span::styled(
+                    format!("  {}", parent.display()),
+                    Style::default().add_modifier(Modifier::DIM),
+                ));
+            }
+            ListItem::new(Line::from(spans)).style(Style::default().fg(theme.text))
+        }).collect();
+
+        let area = centered_rect(70, 15, size);
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!("┤   Jump to: {} ├", picker.query))
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            )
+            .highlight_symbol("▶ ")
+            .highlight_style(
+                Style::default()
+                    .fg(theme.highlight_fg)
+                    .bg(theme.highlight_bg)
+                    .add_modifier(Modifier::BOLD),
+            );
+
+        let mut picker_state = ListState::default();
+        picker_state.select((!results.is_empty()).then_some(picker.selected));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut picker_state);
+    }
+
+    // --- Output device picker overlay ---
+    if let Some(picker) = &app.device_picker {
+        let items: Vec<ListItem> = picker.devices.iter()
+            .map(|name| ListItem::new(name.as_str()).style(Style::default().fg(theme.text)))
+            .collect();
+
+        let area = centered_rect(60, 10, size);
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title("┤ Output Device ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            )
+            .highlight_symbol("▶ ")
+            .highlight_style(
+                Style::default()
+                    .fg(theme.highlight_fg)
+                    .bg(theme.highlight_bg)
+                    .add_modifier(Modifier::BOLD),
+            );
+
+        let mut picker_state = ListState::default();
+        picker_state.select(Some(picker.selected));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut picker_state);
+    }
+
+    // --- Bookmark list overlay ---
+    if let Some(selected) = app.bookmark_picker {
+        let items: Vec<ListItem> = app.bookmarks.iter()
+            .map(|path| {
+                let name = if path.is_dir() { format!("{}/", dir_basename(path)) } else { dir_basename(path) };
+                let parent = path.parent().map(|p| truncate_path(p, 40)).unwrap_or_default();
+                ListItem::new(Line::from(vec![
+                    Span::raw(name),
+                    Span::styled(format!("  {}", parent), Style::default().fg(theme.border)),
+                ]))
+            })
+            .collect();
+
+        let area = centered_rect(70, 14, size);
+        let list = List::new(items)
+            .style(Style::default().fg(theme.text))
+            .block(
+                Block::default()
+                    .title("┤ ★ Bookmarks ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            )
+            .highlight_symbol("▶ ")
+            .highlight_style(
+                Style::default()
+                    .fg(theme.highlight_fg)
+                    .bg(theme.highlight_bg)
+                    .add_modifier(Modifier::BOLD),
+            );
+
+        let mut picker_state = ListState::default();
+        picker_state.select(Some(selected));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut picker_state);
+    }
+
+    // --- Smart playlist picker overlay: the fields, then the values of the chosen one ---
+    if let Some(picker) = &app.playlist_picker {
+        let (title, items): (String, Vec<ListItem>) = match picker.field {
+            None => (
+                "┤ Smart Playlist ├".into(),
+                PlaylistField::ALL.iter()
+                    .map(|field| ListItem::new(format!("By {}", field.label().to_lowercase())))
+                    .collect(),
+            ),
+            Some(field) if picker.values.is_empty() => (
+                format!("┤ Smart Playlist: {} ├", field.label()),
+                vec![ListItem::new("Reading tags...")],
+            ),
+            Some(field) => (
+                format!("┤ Smart Playlist: {} ├", field.label()),
+                picker.values.iter()
+                    .map(|(value, count)| ListItem::new(format!("{} ({})", value, count)))
+                    .collect(),
+            ),
+        };
+        let ready = picker.field.is_none() || !picker.values.is_empty();
+
+        let area = centered_rect(50, 12, size);
+        let list = List::new(items)
+            .style(Style::default().fg(theme.text))
+            .block(
+                Block::default()
+                    .title(title)
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            )
+            .highlight_symbol("▶ ")
+            .highlight_style(
+                Style::default()
+                    .fg(theme.highlight_fg)
+                    .bg(theme.highlight_bg)
+                    .add_modifier(Modifier::BOLD),
+            );
+
+        let mut picker_state = ListState::default();
+        picker_state.select(ready.then_some(picker.selected));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut picker_state);
+    }
+
+    // --- Stats overlay ---
+    if app.show_stats {
+        let lines = vec![
+            Line::from(app.library_summary()),
+            Line::from(format!("Songs played this session: {}", app.songs_played)),
+            Line::from(app.latency_summary()),
+        ];
+        let area = centered_rect(50, lines.len() as u16 + 2, size);
+        let stats = Paragraph::new(lines)
+            .style(Style::default().fg(theme.text))
+            .block(
+                Block::default()
+                    .title("┤   Stats ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(stats, area);
+    }
+
+    // --- History overlay (newest first) ---
+    if app.show_history {
+        let rows: Vec<Row> = app.history.iter()
+            .enumerate()
+            .rev()
+            .map(|(i, (path, timestamp))| {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                Row::new(vec![(i + 1).to_string(), name, history::time_ago(*timestamp)])
+            })
+            .collect();
+
+        let area = centered_rect(70, 20, size);
+        let table = Table::new(rows, [Constraint::Length(5), Constraint::Min(10), Constraint::Length(10)])
+            .header(Row::new(vec!["#", "Filename", "Time"]).style(Style::default().fg(theme.block_text)))
+            .style(Style::default().fg(theme.text))
+            .block(
+                Block::default()
+                    .title("┤   History ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(table, area);
+    }
+
+    // --- Notification history overlay (newest first) ---
+    if app.show_notifications {
+        let rows: Vec<Row> = app.notifications.iter()
+            .rev()
+            .map(|(message, at)| Row::new(vec![history::age(at.elapsed().as_secs()), message.clone()]))
+            .collect();
+
+        let area = centered_rect(70, 20, size);
+        let table = Table::new(rows, [Constraint::Length(10), Constraint::Min(10)])
+            .header(Row::new(vec!["When", "Message"]).style(Style::default().fg(theme.block_text)))
+            .style(Style::default().fg(theme.text))
+            .block(
+                Block::default()
+                    .title("┤ Notifications ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(table, area);
+    }
+
+    // --- Help overlay: configurable keys first, then the fixed ones ---
+    if app.show_help {
+        let mut rows: Vec<Row> = app.config.keys.bindings()
+            .into_iter()
+            .map(|(action, keys)| Row::new(vec![keys.join(" / "), action.label().to_string()]))
+            .collect();
+        rows.extend(FIXED_KEYS.iter().map(|(key, action)| Row::new(vec![key.to_string(), action.to_string()])));
+
+        let area = centered_rect(50, rows.len() as u16 + 3, size);
+        let table = Table::new(rows, [Constraint::Length(14), Constraint::Min(10)])
+            .header(Row::new(vec!["Key", "Action"]).style(Style::default().fg(theme.block_text)))
+            .style(Style::default().fg(theme.text))
+            .block(
+                Block::default()
+                    .title("┤ Keys ├")
+                    .title_style(Style::default().fg(theme.block_text))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(table, area);
+    }
+
+    hit_areas
+}
+
+/// The lyrics panel: the current line highlighted and kept in the middle, its
+/// neighbours dimmed
+fn draw_lyrics(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let block = Block::default()
+        .title("┤ Lyrics ├")
+        .title_style(Style::default().fg(theme.block_text))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border));
+
+    if app.lyrics.is_empty() {
+        let message = if app.playing.is_some() { "No .lrc file for this track" } else { "Nothing playing" };
+        let empty = Paragraph::new(message)
+            .style(Style::default().fg(theme.border))
+            .alignment(Alignment::Center)
+            .block(block);
+        f.render_widget(empty, area);
+        return;
+    }
+
+    let current = app.current_lyric();
+    let lines: Vec<Line> = app.lyrics.iter().enumerate()
+        .map(|(i, (_, text))| {
+            let style = match current {
+                Some(line) if line == i => {
+                    Style::default().fg(theme.highlight_fg).add_modifier(Modifier::BOLD)
                 }
+                Some(line) if line.abs_diff(i) == 1 => {
+                    Style::default().fg(theme.text).add_modifier(Modifier::DIM)
+                }
+                _ => Style::default().fg(theme.border),
+            };
+            Line::styled(text.as_str(), style)
+        })
+        .collect();
+
+    // Scroll so the current line sits in the middle; before the first line, start at the top
+    let middle = block.inner(area).height / 2;
+    let scroll = current.map_or(0, |line| (line as u16).saturating_sub(middle));
+    let lyrics = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .scroll((scroll, 0))
+        .block(block);
+    f.render_widget(lyrics, area);
+}
+
+/// Compact layout for narrow terminals: the selected entry with its neighbours,
+/// a one-line progress bar and the status bar. Overlays are not drawn here and
+/// the mouse is ignored.
+fn draw_mini_mode(f: &mut Frame, app: &mut App, theme: &Theme) -> HitAreas {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Entries around the selection
+            Constraint::Length(1), // Progress bar
+            Constraint::Length(1), // Status bar
+        ])
+        .split(f.area());
+
+    let visible = app.visible_indices();
+    let position = visible.iter().position(|&i| i == app.selected).unwrap_or(0);
+    let rows = (chunks[0].height as usize).min(2 * MINI_MODE_NEIGHBORS + 1);
+    let first = position.saturating_sub(rows / 2).min(visible.len().saturating_sub(rows));
+    let lines: Vec<Line> = visible.iter().enumerate().skip(first).take(rows)
+        .map(|(n, &i)| {
+            let name = &app.files[i];
+            if n == position {
+                Line::styled(
+                    format!("▶ {}", name),
+                    Style::default().fg(theme.highlight_fg).bg(theme.highlight_bg).add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Line::styled(format!("  {}", name), Style::default().fg(theme.text))
             }
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    // [█████░░░░░] 02:15/04:30
+    // A position of zero is the start of the track, not "unknown"
+    let mm_ss = |ms: u64| match ms / 1000 {
+        0 => "00:00".to_string(),
+        secs => App::format_duration(secs),
+    };
+    let times = if app.is_streaming() {
+        format!("{}/live", mm_ss(app.current_time))
+    } else if app.total_time == 0 {
+        "--:--/--:--".to_string()
+    } else {
+        format!("{}/{}", mm_ss(app.current_time), mm_ss(app.total_time))
+    };
+    let bar_width = (chunks[1].width as usize).saturating_sub(times.chars().count() + 3);
+    let filled = ((app.perc_played / 100.0) * bar_width as f32).round() as usize;
+    let filled = filled.min(bar_width);
+    // Streams show a full bar in the border colour, as their end is unknown
+    let (filled, bar_style) = if app.is_streaming() {
+        (bar_width, Style::default().fg(theme.border))
+    } else {
+        (filled, Style::default().fg(theme.title))
+    };
+    let progress = Line::from(vec![
+        Span::styled("[", Style::default().fg(theme.border)),
+        Span::styled("█".repeat(filled), bar_style),
+        Span::styled("░".repeat(bar_width - filled), Style::default().fg(theme.border)),
+        Span::styled("] ", Style::default().fg(theme.border)),
+        Span::styled(times, Style::default().fg(theme.text)),
+    ]);
+    f.render_widget(Paragraph::new(progress), chunks[1]);
+
+    let status = Paragraph::new(Line::styled(status_text(app), status_style(app, theme)))
+        .style(Style::default().fg(theme.status_text));
+    f.render_widget(status, chunks[2]);
+
+    HitAreas::default()
+}
+
+/// The status bar text: the command or search prompt while typing, otherwise
+/// a fresh notification or the status message
+fn status_text(app: &App) -> String {
+    let status = app.current_notification().unwrap_or(app.status());
+    if let Some(url) = &app.url_input {
+        return format!("URL: {}", url);
+    }
+    match (&app.command, &app.search_query) {
+        (Some(command), _) => format!(":{}", command),
+        (None, Some(query)) => match &app.search_error {
+            Some(error) => format!("{} /{}  {}", app.search_mode.indicator(), query, error),
+            None => format!("{} /{}", app.search_mode.indicator(), query),
+        },
+        (None, None) if app.selection_mode => {
+            format!("-- SELECT ({}) -- {}", app.selected_files.len(), status)
         }
+        (None, None) => status.to_string(),
+    }
+}
+
+/// Style of the status bar's current line: red while the search input holds an invalid regex
+fn status_style(app: &App, theme: &Theme) -> Style {
+    let typing_elsewhere = app.url_input.is_some() || app.command.is_some();
+    if app.search_error.is_some() && !typing_elsewhere {
+        Style::default().fg(Color::Red)
+    } else {
+        Style::default().fg(theme.status_text)
     }
+}
 
-    Ok(())
+/// Pad `text` with spaces to `width` terminal columns, or cut it to fit with a
+/// trailing `…`. Wide characters (CJK, full-width) count as two columns.
+fn fit_width(text: &str, width: usize) -> String {
+    let text_width = text.width();
+    if text_width <= width {
+        return format!("{}{}", text, " ".repeat(width - text_width));
+    }
+
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if used + c_width + 1 > width {
+            break;
+        }
+        fitted.push(c);
+        used += c_width;
+    }
+    if width > 0 {
+        fitted.push('…');
+        used += 1;
+    }
+    // A wide character that didn't fit may leave one column over
+    fitted.push_str(&" ".repeat(width - used));
+    fitted
+}
+
+/// Shorten a path to at most `max_chars` terminal columns by dropping leading
+/// components, e.g. `…/music/albums/2024`. Paths that fit are returned as is.
+fn truncate_path(path: &Path, max_chars: usize) -> String {
+    let full = path.to_string_lossy();
+    if full.width() <= max_chars {
+        return full.into_owned();
+    }
+
+    // Keep as many trailing components as fit after the "…/" prefix
+    let mut kept: Vec<String> = Vec::new();
+    let mut len = 1; // "…"
+    for component in path.iter().rev() {
+        let component = component.to_string_lossy();
+        let added = component.width() + 1; // plus its separator
+        if len + added > max_chars {
+            break;
+        }
+        len += added;
+        kept.push(component.into_owned());
+    }
+
+    if kept.is_empty() {
+        // Not even the last component fits: cut it from the left instead
+        let mut room = max_chars.saturating_sub(1);
+        let tail: Vec<char> = full.chars().rev()
+            .take_while(|c| match room.checked_sub(c.width().unwrap_or(0)) {
+                Some(left) => {
+                    room = left;
+                    true
+                }
+                None => false,
+            })
+            .collect();
+        return format!("…{}", tail.into_iter().rev().collect::<String>());
+    }
+
+    kept.reverse();
+    format!("…/{}", kept.join("/"))
+}
+
+/// A rectangle of the given size centered in `area`, shrunk to fit if needed
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONG_NAME: &str = "宋体音乐收藏夹里的一首很长很长的歌曲名字.mp3";
+
+    #[test]
+    fn fit_width_cuts_cjk_names_at_20_columns() {
+        // The tenth character would straddle the column taken by the `…`
+        let fitted = fit_width(LONG_NAME, 20);
+        assert_eq!(fitted, "宋体音乐收藏夹里的… ");
+        assert_eq!(fitted.width(), 20);
+    }
+
+    #[test]
+    fn fit_width_cuts_cjk_names_at_40_columns() {
+        let fitted = fit_width(LONG_NAME, 40);
+        assert_eq!(fitted, "宋体音乐收藏夹里的一首很长很长的歌曲名… ");
+        assert_eq!(fitted.width(), 40);
+    }
+
+    #[test]
+    fn fit_width_pads_short_cjk_names() {
+        let fitted = fit_width("宋体.mp3", 20);
+        assert_eq!(fitted, format!("宋体.mp3{}", " ".repeat(12)));
+        assert_eq!(fitted.width(), 20);
+    }
+
+    #[test]
+    fn truncate_path_keeps_short_paths_whole() {
+        assert_eq!(truncate_path(Path::new("/home/tom/music"), 20), "/home/tom/music");
+        assert_eq!(truncate_path(Path::new("/"), 1), "/");
+    }
+
+    #[test]
+    fn truncate_path_drops_leading_components() {
+        let path = Path::new("/home/tom/music/albums/2024");
+        assert_eq!(truncate_path(path, 20), "…/music/albums/2024");
+        assert_eq!(truncate_path(path, 13), "…/albums/2024");
+        assert_eq!(truncate_path(path, 12), "…/2024");
+        assert_eq!(truncate_path(path, 6), "…/2024");
+    }
+
+    #[test]
+    fn truncate_path_cuts_a_long_last_component_from_the_left() {
+        let path = Path::new("/music/a-very-long-folder-name");
+        assert_eq!(truncate_path(path, 10), "…lder-name");
+        assert_eq!(truncate_path(path, 1), "…");
+    }
+
+    #[test]
+    fn truncate_path_keeps_trailing_cjk_components() {
+        let path = Path::new("/音乐收藏/专辑/二〇二四/宋体.mp3");
+        assert_eq!(truncate_path(path, 20), "…/二〇二四/宋体.mp3");
+        assert_eq!(truncate_path(path, 40), "/音乐收藏/专辑/二〇二四/宋体.mp3");
+    }
+
+    #[test]
+    fn truncate_path_cuts_a_wide_last_component() {
+        let path = Path::new("/音乐收藏/专辑/很长的歌曲名字很长的歌曲名字.mp3");
+        // A wide character straddling the cut is dropped, leaving a column free
+        let cut = truncate_path(path, 20);
+        assert_eq!(cut, "…很长的歌曲名字.mp3");
+        assert_eq!(cut.width(), 19);
+        assert_eq!(truncate_path(path, 40), "…/专辑/很长的歌曲名字很长的歌曲名字.mp3");
+    }
 }